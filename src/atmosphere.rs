@@ -0,0 +1,179 @@
+use std::f32::consts::PI;
+use nalgebra_glm::Vec3;
+use serde::Deserialize;
+use crate::color::Color;
+use crate::fragment::Fragment;
+use crate::light::Light;
+use crate::render::Uniforms;
+use crate::shader::FragmentShader;
+
+// Atmósfera de un planeta: dispersión simple de Rayleigh y Mie integrada a lo
+// largo del rayo de vista dentro de una cáscara esférica. La cáscara se dibuja
+// en la pasada transparente sumando la luz dispersada sobre lo que ya hay.
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(default)]
+pub struct AtmosphereSettings {
+    // Coeficientes de Rayleigh por canal: el azul se dispersa más que el rojo
+    pub color: [f32; 3],
+    // Multiplica los coeficientes (Rayleigh y Mie)
+    pub density: f32,
+    // Grosor de la atmósfera en fracción del radio del planeta
+    pub height: f32,
+    // Coeficiente de Mie (neblina), igual en los tres canales
+    pub mie: f32,
+    // Anisotropía de Mie: cerca de 1 concentra el brillo alrededor del sol
+    pub mie_g: f32,
+    pub intensity: f32,
+}
+
+impl Default for AtmosphereSettings {
+    fn default() -> Self {
+        AtmosphereSettings {
+            color: [0.18, 0.42, 1.0],
+            density: 1.0,
+            height: 0.15,
+            mie: 0.1,
+            mie_g: 0.76,
+            intensity: 8.0,
+        }
+    }
+}
+
+// Muestras a lo largo del rayo de vista y hacia la luz
+const VIEW_SAMPLES: usize = 12;
+const LIGHT_SAMPLES: usize = 4;
+
+// Atmósfera ya colocada en el mundo para el frame actual.
+#[derive(Clone, Copy, Debug)]
+pub struct Atmosphere {
+    pub settings: AtmosphereSettings,
+    pub center: Vec3,
+    pub planet_radius: f32,
+    pub radius: f32,
+}
+
+impl Atmosphere {
+    pub fn new(settings: AtmosphereSettings, center: Vec3, planet_radius: f32) -> Self {
+        Atmosphere {
+            settings,
+            center,
+            planet_radius,
+            radius: planet_radius * (1.0 + settings.height.max(0.001)),
+        }
+    }
+
+    // Luz dispersada hacia `eye` a lo largo del rayo que pasa por `position`.
+    pub fn scatter(&self, lights: &[Light], eye: &Vec3, position: &Vec3) -> Vec3 {
+        let direction = (position - eye).normalize();
+        let Some((near, mut far)) = self.intersect(eye, &direction, self.radius) else {
+            return Vec3::zeros();
+        };
+        // El planeta corta el rayo
+        if let Some((ground, _)) = self.intersect(eye, &direction, self.planet_radius) {
+            if ground > 0.0 {
+                far = far.min(ground);
+            }
+        }
+        let near = near.max(0.0);
+        if far <= near {
+            return Vec3::zeros();
+        }
+
+        // Los coeficientes se escalan con el grosor para que el aspecto no
+        // dependa del tamaño del planeta
+        let thickness = self.radius - self.planet_radius;
+        let rayleigh = Vec3::from(self.settings.color) * (self.settings.density / thickness);
+        let mie = self.settings.mie * self.settings.density / thickness;
+        let rayleigh_height = thickness * 0.25;
+        let mie_height = thickness * 0.1;
+
+        let step = (far - near) / VIEW_SAMPLES as f32;
+        let mut view_depth = (0.0, 0.0);
+        let mut color = Vec3::zeros();
+        for i in 0..VIEW_SAMPLES {
+            let sample = eye + direction * (near + step * (i as f32 + 0.5));
+            let altitude = (sample - self.center).magnitude() - self.planet_radius;
+            let rayleigh_density = (-altitude / rayleigh_height).exp() * step;
+            let mie_density = (-altitude / mie_height).exp() * step;
+            view_depth.0 += rayleigh_density;
+            view_depth.1 += mie_density;
+
+            for light in lights {
+                let Some((to_light, radiance)) = light.incident(&sample) else {
+                    continue;
+                };
+                // Del lado de noche el planeta tapa la luz
+                let Some(light_depth) = self.light_depth(&sample, &to_light, rayleigh_height, mie_height) else {
+                    continue;
+                };
+                let optical_depth = rayleigh * (view_depth.0 + light_depth.0)
+                    + Vec3::repeat(mie * 1.1 * (view_depth.1 + light_depth.1));
+                let transmittance = optical_depth.map(|depth| (-depth).exp());
+
+                let cos_theta = direction.dot(&to_light);
+                let scattered = rayleigh * (rayleigh_phase(cos_theta) * rayleigh_density)
+                    + Vec3::repeat(mie * mie_phase(cos_theta, self.settings.mie_g) * mie_density);
+                color += transmittance.component_mul(&scattered).component_mul(&radiance);
+            }
+        }
+
+        color * self.settings.intensity
+    }
+
+    // Profundidad óptica (Rayleigh, Mie) desde `origin` hasta salir de la
+    // atmósfera hacia la luz; None si el planeta se interpone.
+    fn light_depth(&self, origin: &Vec3, to_light: &Vec3, rayleigh_height: f32, mie_height: f32) -> Option<(f32, f32)> {
+        if let Some((ground, _)) = self.intersect(origin, to_light, self.planet_radius) {
+            if ground > 0.0 {
+                return None;
+            }
+        }
+        let (_, exit) = self.intersect(origin, to_light, self.radius)?;
+        let step = exit.max(0.0) / LIGHT_SAMPLES as f32;
+        let mut depth = (0.0, 0.0);
+        for i in 0..LIGHT_SAMPLES {
+            let sample = origin + to_light * (step * (i as f32 + 0.5));
+            let altitude = ((sample - self.center).magnitude() - self.planet_radius).max(0.0);
+            depth.0 += (-altitude / rayleigh_height).exp() * step;
+            depth.1 += (-altitude / mie_height).exp() * step;
+        }
+        Some(depth)
+    }
+
+    // Distancias de entrada y salida del rayo en la esfera de radio `radius`.
+    fn intersect(&self, origin: &Vec3, direction: &Vec3, radius: f32) -> Option<(f32, f32)> {
+        let offset = origin - self.center;
+        let b = offset.dot(direction);
+        let c = offset.magnitude_squared() - radius * radius;
+        let discriminant = b * b - c;
+        if discriminant < 0.0 {
+            return None;
+        }
+        let root = discriminant.sqrt();
+        Some((-b - root, -b + root))
+    }
+}
+
+fn rayleigh_phase(cos_theta: f32) -> f32 {
+    3.0 / (16.0 * PI) * (1.0 + cos_theta * cos_theta)
+}
+
+// Henyey-Greenstein
+fn mie_phase(cos_theta: f32, g: f32) -> f32 {
+    let g2 = g * g;
+    (1.0 - g2) / (4.0 * PI * (1.0 + g2 - 2.0 * g * cos_theta).max(1e-4).powf(1.5))
+}
+
+// Shader de la cáscara: devuelve la luz dispersada para mezclarla con `add`.
+pub struct AtmosphereShader;
+
+impl FragmentShader for AtmosphereShader {
+    fn shade(&self, fragment: &Fragment, uniforms: &Uniforms, _time: u32) -> (Color, Color) {
+        let Some(atmosphere) = &uniforms.atmosphere else {
+            return (Color::black().with_alpha(0.0), Color::black());
+        };
+        let light = atmosphere.scatter(&uniforms.lights, &uniforms.camera_position, &fragment.world_position);
+        (Color::from_float(light.x, light.y, light.z), Color::black())
+    }
+}
@@ -0,0 +1,233 @@
+use std::sync::Arc;
+use fastnoise_lite::FastNoiseLite;
+use nalgebra_glm::{Vec2, Vec3, Vec4};
+use rayon::prelude::*;
+use serde::Deserialize;
+use crate::color::Color;
+use crate::framebuffer::Framebuffer;
+use crate::noise::MILKY_WAY_NOISE;
+use crate::render::Uniforms;
+use crate::texture::Texture;
+
+// Fondo de la escena. Se calcula por píxel a partir de la dirección de vista,
+// así que gira con la cámara pero no cambia al acercarse (está en el infinito).
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(default)]
+pub struct StarfieldSettings {
+    pub seed: u32,
+    // Fracción de celdas del cielo que tienen una estrella
+    pub density: f32,
+    pub brightness: f32,
+    // Intensidad de la Vía Láctea (0 = sin banda)
+    pub milky_way: f32,
+    // Normal del plano de la galaxia
+    pub milky_way_axis: [f32; 3],
+}
+
+impl Default for StarfieldSettings {
+    fn default() -> Self {
+        StarfieldSettings {
+            seed: 1,
+            density: 0.03,
+            brightness: 3.0,
+            milky_way: 0.12,
+            milky_way_axis: [0.3, 1.0, 0.2],
+        }
+    }
+}
+
+// Celdas por lado en cada cara del cubo de direcciones; cada celda tiene a lo
+// sumo una estrella
+const STAR_CELLS: f32 = 256.0;
+// Ancho angular (en radianes, aprox.) de la banda de la Vía Láctea
+const MILKY_WAY_WIDTH: f32 = 0.2;
+
+#[derive(Default)]
+pub enum Background {
+    // Solo el color de `camera.background`
+    #[default]
+    Solid,
+    Starfield(Starfield),
+    Skybox(Skybox),
+}
+
+impl Background {
+    // Llena el framebuffer con el fondo visto desde la cámara de `uniforms`.
+    pub fn draw(&self, framebuffer: &mut Framebuffer, uniforms: &Uniforms) {
+        // Solo la rotación de la vista: el fondo no tiene posición
+        let mut view = uniforms.view_matrix;
+        view[(0, 3)] = 0.0;
+        view[(1, 3)] = 0.0;
+        view[(2, 3)] = 0.0;
+        let Some(inverse) = (uniforms.projection_matrix * view).try_inverse() else {
+            return;
+        };
+
+        let (width, height) = (framebuffer.width, framebuffer.height);
+        let pixel_direction = |x: f32, y: f32| {
+            let ndc = Vec4::new(x / width as f32 * 2.0 - 1.0, 1.0 - y / height as f32 * 2.0, 1.0, 1.0);
+            let world = inverse * ndc;
+            Vec3::new(world.x, world.y, world.z).normalize()
+        };
+        // Ángulo que cubre un píxel, para que las estrellas midan un píxel
+        let center = pixel_direction(width as f32 * 0.5, height as f32 * 0.5);
+        let pixel_angle = (pixel_direction(width as f32 * 0.5 + 1.0, height as f32 * 0.5) - center).magnitude();
+
+        let radiance: &(dyn Fn(&Vec3) -> Vec3 + Sync) = match self {
+            // `clear` ya llenó el framebuffer con el color de la cámara
+            Background::Solid => return,
+            Background::Starfield(starfield) => &|direction| starfield.radiance(direction, pixel_angle),
+            Background::Skybox(skybox) => &|direction| skybox.radiance(direction),
+        };
+
+        framebuffer.hdr_buffer.par_chunks_mut(width).enumerate().for_each(|(y, row)| {
+            for (x, pixel) in row.iter_mut().enumerate() {
+                let radiance = radiance(&pixel_direction(x as f32 + 0.5, y as f32 + 0.5));
+                *pixel = Color::from_float(radiance.x, radiance.y, radiance.z);
+            }
+        });
+    }
+}
+
+pub struct Starfield {
+    pub settings: StarfieldSettings,
+    noise: FastNoiseLite,
+    axis: Vec3,
+}
+
+impl Starfield {
+    pub fn new(settings: StarfieldSettings) -> Self {
+        let mut noise = MILKY_WAY_NOISE.create();
+        noise.set_seed(Some(settings.seed as i32));
+        Starfield {
+            axis: Vec3::from(settings.milky_way_axis).normalize(),
+            settings,
+            noise,
+        }
+    }
+
+    pub fn radiance(&self, direction: &Vec3, pixel_angle: f32) -> Vec3 {
+        let band = (-(direction.dot(&self.axis) / MILKY_WAY_WIDTH).powi(2)).exp();
+        self.milky_way(direction, band) + self.star(direction, pixel_angle, band)
+    }
+
+    // Nubes de estrellas a lo largo de la banda, cortadas por franjas de polvo.
+    fn milky_way(&self, direction: &Vec3, band: f32) -> Vec3 {
+        if self.settings.milky_way <= 0.0 || band < 1e-3 {
+            return Vec3::zeros();
+        }
+        let p = direction * 100.0;
+        let clouds = self.noise.get_noise_3d(p.x, p.y, p.z) * 0.5 + 0.5;
+        let dust = self.noise.get_noise_3d(p.x * 2.5 + 31.0, p.y * 2.5, p.z * 2.5) * 0.5 + 0.5;
+        let glow = band * clouds * clouds * (1.0 - 0.7 * dust * band);
+        Vec3::new(0.75, 0.8, 1.0) * (glow * self.settings.milky_way)
+    }
+
+    fn star(&self, direction: &Vec3, pixel_angle: f32, band: f32) -> Vec3 {
+        let (face, uv) = cube_face(direction);
+        let cell = (uv * STAR_CELLS).map(f32::floor);
+        let hash = hash(self.settings.seed, face as u32, cell.x as u32, cell.y as u32);
+
+        // La banda de la galaxia tiene más estrellas
+        let density = self.settings.density * (1.0 + 2.0 * band * self.settings.milky_way.min(1.0));
+        if random(hash, 0) >= density {
+            return Vec3::zeros();
+        }
+
+        // Posición dentro de la celda, lejos de sus bordes para no cortar la estrella
+        let offset = Vec2::new(0.2 + 0.6 * random(hash, 1), 0.2 + 0.6 * random(hash, 2));
+        let star = cube_direction(face, (cell + offset) / STAR_CELLS).normalize();
+        let distance = (direction - star).magnitude() / pixel_angle.max(1e-6);
+        let falloff = (-distance * distance * 1.5).exp();
+
+        // Casi todas son débiles y unas pocas muy brillantes
+        let magnitude = 0.03 + random(hash, 3).powi(12);
+        temperature_color(random(hash, 4)) * (self.settings.brightness * magnitude * falloff)
+    }
+}
+
+// Color aproximado de una estrella según su temperatura: 0 es rojiza (fría) y
+// 1 azulada (caliente).
+fn temperature_color(temperature: f32) -> Vec3 {
+    let cool = Vec3::new(1.0, 0.55, 0.3);
+    let white = Vec3::new(1.0, 0.95, 0.9);
+    let hot = Vec3::new(0.6, 0.72, 1.0);
+    if temperature < 0.5 {
+        cool.lerp(&white, temperature * 2.0)
+    } else {
+        white.lerp(&hot, temperature * 2.0 - 1.0)
+    }
+}
+
+// Cubo de seis imágenes en el orden +X, -X, +Y, -Y, +Z, -Z.
+pub struct Skybox {
+    pub faces: [Arc<Texture>; 6],
+}
+
+impl Skybox {
+    pub fn radiance(&self, direction: &Vec3) -> Vec3 {
+        let (face, uv) = cube_face(direction);
+        self.faces[face].sample_vec3(uv, 0.0)
+    }
+}
+
+// Cara del cubo (mismo orden que `Skybox::faces`) y coordenadas uv en [0, 1]
+// de una dirección, con la convención de los cube maps de OpenGL.
+fn cube_face(direction: &Vec3) -> (usize, Vec2) {
+    let abs = direction.abs();
+    let (face, major, s, t) = if abs.x >= abs.y && abs.x >= abs.z {
+        if direction.x > 0.0 {
+            (0, abs.x, -direction.z, -direction.y)
+        } else {
+            (1, abs.x, direction.z, -direction.y)
+        }
+    } else if abs.y >= abs.z {
+        if direction.y > 0.0 {
+            (2, abs.y, direction.x, direction.z)
+        } else {
+            (3, abs.y, direction.x, -direction.z)
+        }
+    } else if direction.z > 0.0 {
+        (4, abs.z, direction.x, -direction.y)
+    } else {
+        (5, abs.z, -direction.x, -direction.y)
+    };
+    (face, Vec2::new((s / major + 1.0) * 0.5, (t / major + 1.0) * 0.5))
+}
+
+// Inversa de `cube_face` (sin normalizar).
+fn cube_direction(face: usize, uv: Vec2) -> Vec3 {
+    let s = uv.x * 2.0 - 1.0;
+    let t = uv.y * 2.0 - 1.0;
+    match face {
+        0 => Vec3::new(1.0, -t, -s),
+        1 => Vec3::new(-1.0, -t, s),
+        2 => Vec3::new(s, 1.0, t),
+        3 => Vec3::new(s, -1.0, -t),
+        4 => Vec3::new(s, -t, 1.0),
+        _ => Vec3::new(-s, -t, -1.0),
+    }
+}
+
+fn hash(seed: u32, face: u32, x: u32, y: u32) -> u32 {
+    let mut h = seed.wrapping_mul(0x9E37_79B9) ^ face.wrapping_mul(0x85EB_CA6B);
+    h ^= x.wrapping_mul(0xC2B2_AE35);
+    h = h.rotate_left(13).wrapping_mul(5).wrapping_add(0xE654_6B64);
+    h ^= y.wrapping_mul(0x27D4_EB2F);
+    mix(h)
+}
+
+// Número pseudoaleatorio en [0, 1) derivado de `hash`; `index` da valores
+// independientes para la misma celda.
+fn random(hash: u32, index: u32) -> f32 {
+    (mix(hash ^ index.wrapping_mul(0x68E3_1DA4)) >> 8) as f32 / (1u32 << 24) as f32
+}
+
+fn mix(mut h: u32) -> u32 {
+    h ^= h >> 16;
+    h = h.wrapping_mul(0x7FEB_352D);
+    h ^= h >> 15;
+    h = h.wrapping_mul(0x846C_A68B);
+    h ^ (h >> 16)
+}
@@ -0,0 +1,123 @@
+use lab04::scene::Scene;
+use lab04::shader::ShaderRegistry;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::process;
+use lab04::framebuffer::{Framebuffer, RenderableToFile};
+use lab04::render::{create_perspective_matrix, create_view_matrix, create_viewport_matrix, Uniforms};
+
+// Render sin ventana: genera N frames y los guarda como PNG numerados.
+//
+// Uso: headless [--frames N] [--width W] [--height H] [--scene FILE] [--system N] [--start T] [--out DIR] [--no-bloom]
+struct Options {
+    frames: u32,
+    width: usize,
+    height: usize,
+    scene: String,
+    system: usize,
+    start_time: u32,
+    out_dir: PathBuf,
+    bloom: bool,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            frames: 60,
+            width: 600,
+            height: 600,
+            scene: "scenes/default.toml".to_string(),
+            system: 1,
+            start_time: 0,
+            out_dir: PathBuf::from("frames"),
+            bloom: true,
+        }
+    }
+}
+
+fn parse_args() -> Result<Options, String> {
+    let mut options = Options::default();
+    let mut args = env::args().skip(1);
+
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("missing value for {}", arg));
+        match arg.as_str() {
+            "--frames" => options.frames = parse_value(&arg, &value()?)?,
+            "--width" => options.width = parse_value(&arg, &value()?)?,
+            "--height" => options.height = parse_value(&arg, &value()?)?,
+            "--scene" => options.scene = value()?,
+            "--system" => options.system = parse_value(&arg, &value()?)?,
+            "--start" => options.start_time = parse_value(&arg, &value()?)?,
+            "--out" => options.out_dir = PathBuf::from(value()?),
+            "--no-bloom" => options.bloom = false,
+            _ => return Err(format!("unknown argument {}", arg)),
+        }
+    }
+
+    if options.width == 0 || options.height == 0 {
+        return Err("width and height must be greater than zero".to_string());
+    }
+    if options.system == 0 {
+        return Err("systems are numbered from 1".to_string());
+    }
+
+    Ok(options)
+}
+
+fn parse_value<T: std::str::FromStr>(name: &str, value: &str) -> Result<T, String> {
+    value.parse().map_err(|_| format!("invalid value for {}: {}", name, value))
+}
+
+fn main() {
+    let options = match parse_args() {
+        Ok(options) => options,
+        Err(error) => {
+            eprintln!("{}", error);
+            eprintln!("usage: headless [--frames N] [--width W] [--height H] [--scene FILE] [--system N] [--start T] [--out DIR] [--no-bloom]");
+            process::exit(2);
+        }
+    };
+
+    let registry = ShaderRegistry::with_builtin();
+    let mut scene = match Scene::load(&options.scene, &registry) {
+        Ok(scene) => scene,
+        Err(error) => {
+            eprintln!("{}: {}", options.scene, error);
+            process::exit(1);
+        }
+    };
+    if !options.bloom {
+        scene.post.set_enabled("bloom", false);
+    }
+    if options.system > scene.systems.len() {
+        eprintln!("{} only has {} systems", options.scene, scene.systems.len());
+        process::exit(2);
+    }
+
+    fs::create_dir_all(&options.out_dir).expect("Failed to create output directory");
+
+    let mut framebuffer = Framebuffer::new(options.width, options.height);
+    framebuffer.set_background_color(scene.camera.background);
+
+    let view_matrix = create_view_matrix(scene.camera.eye, scene.camera.center, scene.camera.up);
+    let projection_matrix = create_perspective_matrix(options.width as f32, options.height as f32);
+    let viewport_matrix = create_viewport_matrix(options.width as f32, options.height as f32);
+
+    for frame in 0..options.frames {
+        let time = options.start_time + frame;
+        framebuffer.clear();
+
+        let uniforms = Uniforms::new(view_matrix, projection_matrix, viewport_matrix, time);
+
+        scene.render_system(&mut framebuffer, &uniforms, options.system - 1, time);
+        scene.post.apply(&mut framebuffer, time);
+        framebuffer.tone_map(&scene.tone_mapping);
+
+        let path = options.out_dir.join(format!("frame_{:04}.png", frame));
+        framebuffer
+            .render_png(path.to_str().expect("Invalid output path"))
+            .expect("Failed to write frame");
+        println!("{}", path.display());
+    }
+}
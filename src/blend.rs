@@ -0,0 +1,36 @@
+use serde::Deserialize;
+use crate::color::Color;
+
+// Cómo se combina un fragmento transparente con lo que ya hay en el framebuffer.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BlendMode {
+    #[default]
+    Normal,
+    Multiply,
+    Add,
+    Subtract,
+    Screen,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct BlendState {
+    pub mode: BlendMode,
+    // Si es false el fragmento no escribe profundidad (no tapa a los que vienen detrás)
+    pub depth_write: bool,
+}
+
+impl BlendState {
+    // Mezcla con el modo elegido y luego interpola según la opacidad del fragmento.
+    // En `Normal` eso es source-over: un fragmento opaco negro también tapa.
+    pub fn apply(&self, destination: Color, source: Color) -> Color {
+        let blended = match self.mode {
+            BlendMode::Normal => source,
+            BlendMode::Multiply => destination.blend_multiply(&source),
+            BlendMode::Add => destination.blend_add(&source),
+            BlendMode::Subtract => destination.blend_subtract(&source),
+            BlendMode::Screen => destination.blend_screen(&source),
+        };
+        destination.lerp(&blended, source.a).with_alpha(destination.a)
+    }
+}
@@ -0,0 +1,204 @@
+use rayon::prelude::*;
+use serde::Deserialize;
+use crate::color::Color;
+use crate::framebuffer::Framebuffer;
+use crate::post::PostEffect;
+use crate::render::gaussian_blur;
+
+// Bloom sobre el color HDR, antes del tone mapping. Un bright-pass separa lo
+// que pasa del umbral y toda la luz emitida (del buffer emisivo), se reduce
+// a la mitad varias veces y se vuelve a ampliar sumando cada nivel, así el
+// brillo se abre a varias escalas sin un kernel gigante.
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(default)]
+pub struct BloomSettings {
+    // Brillo (máximo de los canales) a partir del cual un píxel florece
+    pub threshold: f32,
+    // Ancho de la transición suave alrededor del umbral
+    pub knee: f32,
+    pub intensity: f32,
+    // Niveles de la cadena de reducción (cada uno a la mitad del anterior)
+    pub levels: usize,
+}
+
+impl Default for BloomSettings {
+    fn default() -> Self {
+        BloomSettings {
+            threshold: 1.0,
+            knee: 0.5,
+            intensity: 0.6,
+            levels: 5,
+        }
+    }
+}
+
+// Desenfoque de cada nivel, en píxeles del nivel; como cada nivel mide la
+// mitad que el anterior, el brillo se abre el doble en cada uno
+const LEVEL_SIGMA: f32 = 1.0;
+
+// Una imagen de la cadena de reducción.
+struct Level {
+    width: usize,
+    height: usize,
+    pixels: Vec<Color>,
+}
+
+impl Level {
+    fn new(width: usize, height: usize) -> Self {
+        Level { width, height, pixels: vec![Color::black(); width * height] }
+    }
+
+    fn blur(&mut self) {
+        gaussian_blur(&mut self.pixels, self.width, self.height, LEVEL_SIGMA);
+    }
+
+    fn texel(&self, x: i32, y: i32) -> Color {
+        let x = x.clamp(0, self.width as i32 - 1) as usize;
+        let y = y.clamp(0, self.height as i32 - 1) as usize;
+        self.pixels[y * self.width + x]
+    }
+
+    fn bilinear(&self, x: f32, y: f32) -> Color {
+        let (x0, y0) = (x.floor(), y.floor());
+        let (tx, ty) = (x - x0, y - y0);
+        let (x0, y0) = (x0 as i32, y0 as i32);
+        let top = self.texel(x0, y0).lerp(&self.texel(x0 + 1, y0), tx);
+        let bottom = self.texel(x0, y0 + 1).lerp(&self.texel(x0 + 1, y0 + 1), tx);
+        top.lerp(&bottom, ty)
+    }
+}
+
+pub struct Bloom {
+    pub settings: BloomSettings,
+    // Se conservan entre frames para no reservar memoria cada vez
+    levels: Vec<Level>,
+}
+
+impl Bloom {
+    pub fn new(settings: BloomSettings) -> Self {
+        Bloom { settings, levels: Vec::new() }
+    }
+
+    pub fn apply(&mut self, framebuffer: &mut Framebuffer) {
+        let settings = self.settings;
+        if settings.intensity <= 0.0 || settings.levels == 0 {
+            return;
+        }
+        self.resize(framebuffer.width, framebuffer.height);
+        if self.levels.is_empty() {
+            return;
+        }
+
+        // Bright-pass a media resolución: promedio de 2x2 píxeles
+        let source = &framebuffer.hdr_buffer;
+        let emissive = &framebuffer.emissive_buffer;
+        let width = framebuffer.width;
+        let height = framebuffer.height;
+        let first = &mut self.levels[0];
+        let first_width = first.width;
+        first.pixels.par_chunks_mut(first_width).enumerate().for_each(|(y, row)| {
+            for (x, pixel) in row.iter_mut().enumerate() {
+                let mut sum = Color::black();
+                for (sx, sy) in [(2 * x, 2 * y), (2 * x + 1, 2 * y), (2 * x, 2 * y + 1), (2 * x + 1, 2 * y + 1)] {
+                    let index = sy.min(height - 1) * width + sx.min(width - 1);
+                    sum = sum + bright_pass(source[index], emissive[index], &settings);
+                }
+                *pixel = sum * 0.25;
+            }
+        });
+        first.blur();
+
+        // Cadena de reducción: cada nivel se reduce del anterior y se desenfoca
+        for level in 1..self.levels.len() {
+            let (previous, rest) = self.levels.split_at_mut(level);
+            downsample(&previous[level - 1], &mut rest[0]);
+            rest[0].blur();
+        }
+
+        // Ampliación: cada nivel suma el siguiente, más pequeño, ya ampliado
+        for level in (1..self.levels.len()).rev() {
+            let (larger, smaller) = self.levels.split_at_mut(level);
+            upsample_add(&smaller[0], &mut larger[level - 1]);
+        }
+
+        let bloom = &self.levels[0];
+        let scale = settings.intensity / self.levels.len() as f32;
+        let ratio_x = bloom.width as f32 / width as f32;
+        let ratio_y = bloom.height as f32 / height as f32;
+        framebuffer.hdr_buffer.par_chunks_mut(width).enumerate().for_each(|(y, row)| {
+            for (x, pixel) in row.iter_mut().enumerate() {
+                let glow = bloom.bilinear((x as f32 + 0.5) * ratio_x - 0.5, (y as f32 + 0.5) * ratio_y - 0.5) * scale;
+                *pixel = *pixel + glow;
+            }
+        });
+    }
+
+    fn resize(&mut self, width: usize, height: usize) {
+        let mut sizes = Vec::new();
+        let (mut level_width, mut level_height) = (width, height);
+        for _ in 0..self.settings.levels {
+            level_width = level_width.div_ceil(2);
+            level_height = level_height.div_ceil(2);
+            sizes.push((level_width, level_height));
+            if level_width == 1 && level_height == 1 {
+                break;
+            }
+        }
+        let matches = sizes.len() == self.levels.len()
+            && sizes.iter().zip(&self.levels).all(|(&(w, h), level)| level.width == w && level.height == h);
+        if !matches {
+            self.levels = sizes.into_iter().map(|(w, h)| Level::new(w, h)).collect();
+        }
+    }
+}
+
+impl PostEffect for Bloom {
+    fn name(&self) -> &'static str {
+        "bloom"
+    }
+
+    fn apply(&mut self, framebuffer: &mut Framebuffer, _time: u32) {
+        Bloom::apply(self, framebuffer);
+    }
+}
+
+// Parte del color que florece, con la rodilla suave de `knee` alrededor del
+// umbral. La emisión florece entera, con su propio color, aunque no pase el
+// umbral; al resto del píxel se le aplica el umbral normalmente.
+fn bright_pass(color: Color, emission: Color, settings: &BloomSettings) -> Color {
+    let surface = Color::from_float(
+        (color.r - emission.r).max(0.0),
+        (color.g - emission.g).max(0.0),
+        (color.b - emission.b).max(0.0),
+    );
+    let brightness = surface.r.max(surface.g).max(surface.b);
+    let knee = settings.knee.max(1e-5);
+    let soft = (brightness - settings.threshold + knee).clamp(0.0, 2.0 * knee);
+    let soft = soft * soft / (4.0 * knee);
+    let contribution = soft.max(brightness - settings.threshold) / brightness.max(1e-5);
+    surface * contribution.max(0.0) + emission
+}
+
+// Reduce a la mitad promediando 2x2 píxeles; el desenfoque va después.
+fn downsample(source: &Level, target: &mut Level) {
+    let target_width = target.width;
+    target.pixels.par_chunks_mut(target_width).enumerate().for_each(|(y, row)| {
+        for (x, pixel) in row.iter_mut().enumerate() {
+            let (sx, sy) = (2 * x as i32, 2 * y as i32);
+            let sum = source.texel(sx, sy) + source.texel(sx + 1, sy) + source.texel(sx, sy + 1) + source.texel(sx + 1, sy + 1);
+            *pixel = sum * 0.25;
+        }
+    });
+}
+
+fn upsample_add(source: &Level, target: &mut Level) {
+    let ratio_x = source.width as f32 / target.width as f32;
+    let ratio_y = source.height as f32 / target.height as f32;
+    let target_width = target.width;
+    target.pixels.par_chunks_mut(target_width).enumerate().for_each(|(y, row)| {
+        for (x, pixel) in row.iter_mut().enumerate() {
+            *pixel = *pixel + source.bilinear((x as f32 + 0.5) * ratio_x - 0.5, (y as f32 + 0.5) * ratio_y - 0.5);
+        }
+    });
+}
@@ -0,0 +1,97 @@
+use nalgebra_glm::{Mat4, Vec3, Vec4};
+use crate::vertex::Vertex;
+
+// Recorte en espacio de clip, entre el vertex shader y el rasterizador.
+//
+// Un punto está dentro del frustum si -w <= x, y, z <= w. Los triángulos se
+// recortan contra los seis planos (Sutherland-Hodgman) antes de dividir por `w`,
+// así los vértices detrás de la cámara nunca llegan a la división.
+
+const FRUSTUM_PLANES: [Vec4; 6] = [
+    Vec4::new(0.0, 0.0, 1.0, 1.0),  // near:   z + w >= 0
+    Vec4::new(0.0, 0.0, -1.0, 1.0), // far:    w - z >= 0
+    Vec4::new(1.0, 0.0, 0.0, 1.0),  // left:   x + w >= 0
+    Vec4::new(-1.0, 0.0, 0.0, 1.0), // right:  w - x >= 0
+    Vec4::new(0.0, 1.0, 0.0, 1.0),  // bottom: y + w >= 0
+    Vec4::new(0.0, -1.0, 0.0, 1.0), // top:    w - y >= 0
+];
+
+// Recorta un triángulo y devuelve los triángulos resultantes (en abanico).
+pub fn clip_triangle(triangle: &[Vertex; 3]) -> Vec<[Vertex; 3]> {
+    let mut inside_all = true;
+    for plane in &FRUSTUM_PLANES {
+        let distances = triangle.iter().map(|v| plane.dot(&v.clip_position));
+        let (mut inside, mut outside) = (0, 0);
+        for distance in distances {
+            if distance >= 0.0 { inside += 1 } else { outside += 1 }
+        }
+        if inside == 0 {
+            return Vec::new();
+        }
+        if outside > 0 {
+            inside_all = false;
+        }
+    }
+    if inside_all {
+        return vec![triangle.clone()];
+    }
+
+    let mut polygon = triangle.to_vec();
+    for plane in &FRUSTUM_PLANES {
+        polygon = clip_polygon(&polygon, plane);
+        if polygon.len() < 3 {
+            return Vec::new();
+        }
+    }
+
+    (1..polygon.len() - 1)
+        .map(|i| [polygon[0].clone(), polygon[i].clone(), polygon[i + 1].clone()])
+        .collect()
+}
+
+fn clip_polygon(polygon: &[Vertex], plane: &Vec4) -> Vec<Vertex> {
+    let mut output = Vec::with_capacity(polygon.len() + 1);
+
+    for i in 0..polygon.len() {
+        let current = &polygon[i];
+        let next = &polygon[(i + 1) % polygon.len()];
+        let current_distance = plane.dot(&current.clip_position);
+        let next_distance = plane.dot(&next.clip_position);
+
+        if current_distance >= 0.0 {
+            output.push(current.clone());
+        }
+        if (current_distance >= 0.0) != (next_distance >= 0.0) {
+            let t = current_distance / (current_distance - next_distance);
+            output.push(lerp_vertex(current, next, t));
+        }
+    }
+
+    output
+}
+
+fn lerp_vertex(a: &Vertex, b: &Vertex, t: f32) -> Vertex {
+    Vertex {
+        position: a.position.lerp(&b.position, t),
+        normal: a.normal.lerp(&b.normal, t),
+        tex_coords: a.tex_coords.lerp(&b.tex_coords, t),
+        color: a.color.lerp(&b.color, t),
+        transformed_position: a.transformed_position.lerp(&b.transformed_position, t),
+        transformed_normal: a.transformed_normal.lerp(&b.transformed_normal, t),
+        world_position: a.world_position.lerp(&b.world_position, t),
+        clip_position: a.clip_position.lerp(&b.clip_position, t),
+    }
+}
+
+// División de perspectiva y transformación de viewport.
+pub fn to_screen(vertex: &mut Vertex, viewport_matrix: &Mat4) {
+    let w = vertex.clip_position.w;
+    let ndc = Vec4::new(
+        vertex.clip_position.x / w,
+        vertex.clip_position.y / w,
+        vertex.clip_position.z / w,
+        1.0,
+    );
+    let screen = viewport_matrix * ndc;
+    vertex.transformed_position = Vec3::new(screen.x, screen.y, screen.z);
+}
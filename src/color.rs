@@ -1,49 +1,73 @@
 use std::fmt;
 use std::ops::{Add, Mul};
 
-#[derive(Debug, Clone, Copy)]
+// Color RGB en punto flotante y en espacio lineal: las mezclas, `lerp` y la
+// iluminación operan sobre luz lineal. Los canales no se saturan: pueden pasar
+// de 1.0 (HDR) y se comprimen al rango de pantalla en el tone mapping.
+//
+// Los bytes y los valores hexadecimales están en sRGB: `new` y `from_hex`
+// decodifican a lineal, y `to_hex`/`to_u32` codifican a sRGB para la salida.
+//
+// `a` es la opacidad (1.0 = opaco); solo la usa la pasada transparente.
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Color {
-    pub r: u8,
-    pub g: u8,
-    pub b: u8,
+    pub r: f32,
+    pub g: f32,
+    pub b: f32,
+    pub a: f32,
 }
 
 impl Color {
     pub fn new(r: u8, g: u8, b: u8) -> Self {
-        Color { r, g, b }
+        Color::from_srgb(r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0)
     }
     pub fn black() -> Self {
-        Color { r: 0, g: 0, b: 0 }
+        Color { r: 0.0, g: 0.0, b: 0.0, a: 1.0 }
     }
+    // Valores ya lineales.
     pub fn from_float(r: f32, g: f32, b: f32) -> Self {
-        Color {
-            r: (r.clamp(0.0, 1.0) * 255.0) as u8,
-            g: (g.clamp(0.0, 1.0) * 255.0) as u8,
-            b: (b.clamp(0.0, 1.0) * 255.0) as u8,
-        }
+        Color { r, g, b, a: 1.0 }
+    }
+    pub fn with_alpha(self, a: f32) -> Self {
+        Color { a: a.clamp(0.0, 1.0), ..self }
+    }
+    // Valores en sRGB (como los de un selector de color), convertidos a lineal.
+    pub fn from_srgb(r: f32, g: f32, b: f32) -> Self {
+        Color::from_float(srgb_to_linear(r), srgb_to_linear(g), srgb_to_linear(b))
+    }
+    pub fn to_linear(&self) -> Color {
+        Color::from_srgb(self.r, self.g, self.b).with_alpha(self.a)
+    }
+    pub fn to_srgb(&self) -> Color {
+        Color::from_float(linear_to_srgb(self.r), linear_to_srgb(self.g), linear_to_srgb(self.b)).with_alpha(self.a)
     }
     pub fn from_hex(hex: u32) -> Self {
         let r = ((hex >> 16) & 0xFF) as u8;
         let g = ((hex >> 8) & 0xFF) as u8;
         let b = (hex & 0xFF) as u8;
-        Color { r, g, b }
+        Color::new(r, g, b)
     }
     pub fn to_hex(&self) -> u32 {
-        ((self.r as u32) << 16) | ((self.g as u32) << 8) | (self.b as u32)
+        let srgb = self.to_srgb();
+        (to_byte(srgb.r) << 16) | (to_byte(srgb.g) << 8) | to_byte(srgb.b)
     }
     pub fn to_u32(self) -> u32 {
-        (self.r as u32) << 16 | (self.g as u32) << 8 | (self.b as u32)
+        self.to_hex()
     }
     pub fn lerp(&self, other: &Color, t: f32) -> Self {
         let t = t.clamp(0.0, 1.0);
         Color {
-            r: (self.r as f32 + (other.r as f32 - self.r as f32) * t).round() as u8,
-            g: (self.g as f32 + (other.g as f32 - self.g as f32) * t).round() as u8,
-            b: (self.b as f32 + (other.b as f32 - self.b as f32) * t).round() as u8,
+            r: self.r + (other.r - self.r) * t,
+            g: self.g + (other.g - self.g) * t,
+            b: self.b + (other.b - self.b) * t,
+            a: self.a + (other.a - self.a) * t,
         }
     }
     pub fn is_black(&self) -> bool {
-        self.r == 0 && self.g == 0 && self.b == 0
+        self.r == 0.0 && self.g == 0.0 && self.b == 0.0
+    }
+    pub fn luminance(&self) -> f32 {
+        0.2126 * self.r + 0.7152 * self.g + 0.0722 * self.b
     }
     pub fn blend_normal(&self, blend: &Color) -> Color {
         if blend.is_black() {
@@ -53,42 +77,57 @@ impl Color {
         }
     }
     pub fn blend_multiply(&self, blend: &Color) -> Color {
-        Color::new(
-            ((self.r as f32 * blend.r as f32) / 255.0) as u8,
-            ((self.g as f32 * blend.g as f32) / 255.0) as u8,
-            ((self.b as f32 * blend.b as f32) / 255.0) as u8,
-        )
+        Color::from_float(self.r * blend.r, self.g * blend.g, self.b * blend.b).with_alpha(self.a)
     }
     pub fn blend_add(&self, blend: &Color) -> Color {
-        Color::new(
-            (self.r as u16 + blend.r as u16).min(255) as u8,
-            (self.g as u16 + blend.g as u16).min(255) as u8,
-            (self.b as u16 + blend.b as u16).min(255) as u8,
-        )
+        Color::from_float(self.r + blend.r, self.g + blend.g, self.b + blend.b).with_alpha(self.a)
     }
     pub fn blend_subtract(&self, blend: &Color) -> Color {
-        let r = (self.r as i16 - blend.r as i16).max(0).min(255) as u8;
-        let g = (self.g as i16 - blend.g as i16).max(0).min(255) as u8;
-        let b = (self.b as i16 - blend.b as i16).max(0).min(255) as u8;
-
-        Color::new(r, g, b)
+        Color::from_float(
+            (self.r - blend.r).max(0.0),
+            (self.g - blend.g).max(0.0),
+            (self.b - blend.b).max(0.0),
+        ).with_alpha(self.a)
     }
     pub fn blend_screen(&self, blend: &Color) -> Color {
-        Color::new(
-            255 - ((255 - self.r as u16) * (255 - blend.r as u16) / 255) as u8,
-            255 - ((255 - self.g as u16) * (255 - blend.g as u16) / 255) as u8,
-            255 - ((255 - self.b as u16) * (255 - blend.b as u16) / 255) as u8,
-        )
+        Color::from_float(
+            1.0 - (1.0 - self.r) * (1.0 - blend.r),
+            1.0 - (1.0 - self.g) * (1.0 - blend.g),
+            1.0 - (1.0 - self.b) * (1.0 - blend.b),
+        ).with_alpha(self.a)
     }
 }
+
+pub fn srgb_to_linear(value: f32) -> f32 {
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+pub fn linear_to_srgb(value: f32) -> f32 {
+    if value <= 0.0031308 {
+        value * 12.92
+    } else {
+        1.055 * value.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+// Canal en [0, 1] a byte, con redondeo.
+fn to_byte(value: f32) -> u32 {
+    (value.clamp(0.0, 1.0) * 255.0).round() as u32
+}
+
 impl Add for Color {
     type Output = Color;
 
     fn add(self, other: Color) -> Color {
         Color {
-            r: self.r.saturating_add(other.r),
-            g: self.g.saturating_add(other.g),
-            b: self.b.saturating_add(other.b),
+            r: self.r + other.r,
+            g: self.g + other.g,
+            b: self.b + other.b,
+            a: self.a,
         }
     }
 }
@@ -97,14 +136,15 @@ impl Mul<f32> for Color {
 
     fn mul(self, scalar: f32) -> Color {
         Color {
-            r: (self.r as f32 * scalar).clamp(0.0, 255.0) as u8,
-            g: (self.g as f32 * scalar).clamp(0.0, 255.0) as u8,
-            b: (self.b as f32 * scalar).clamp(0.0, 255.0) as u8,
+            r: (self.r * scalar).max(0.0),
+            g: (self.g * scalar).max(0.0),
+            b: (self.b * scalar).max(0.0),
+            a: self.a,
         }
     }
 }
 impl fmt::Display for Color {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "Color(r: {}, g: {}, b: {})", self.r, self.g, self.b)
+        write!(f, "Color(r: {}, g: {}, b: {}, a: {})", self.r, self.g, self.b, self.a)
     }
 }
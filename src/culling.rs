@@ -0,0 +1,107 @@
+use nalgebra_glm::{Mat4, Vec3, Vec4};
+use serde::Deserialize;
+use crate::vertex::Vertex;
+
+// Qué caras se descartan antes de rasterizar.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CullMode {
+    None,
+    #[default]
+    Back,
+    Front,
+}
+
+// Orden de los vértices de una cara frontal, visto desde la cámara.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FrontFace {
+    #[default]
+    Ccw,
+    Cw,
+}
+
+// Recibe el triángulo ya en coordenadas de pantalla. El viewport invierte el
+// eje y, así que un triángulo antihorario en NDC queda horario en pantalla.
+pub fn is_face_culled(triangle: &[Vertex; 3], cull_mode: CullMode, front_face: FrontFace) -> bool {
+    if cull_mode == CullMode::None {
+        return false;
+    }
+
+    let a = triangle[0].transformed_position;
+    let b = triangle[1].transformed_position;
+    let c = triangle[2].transformed_position;
+    let signed_area = (b.x - a.x) * (c.y - a.y) - (c.x - a.x) * (b.y - a.y);
+
+    let counter_clockwise = signed_area < 0.0;
+    let front = match front_face {
+        FrontFace::Ccw => counter_clockwise,
+        FrontFace::Cw => !counter_clockwise,
+    };
+
+    match cull_mode {
+        CullMode::Back => !front,
+        CullMode::Front => front,
+        CullMode::None => false,
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct BoundingSphere {
+    pub center: Vec3,
+    pub radius: f32,
+}
+
+impl BoundingSphere {
+    pub fn from_vertices(vertices: &[Vertex]) -> Self {
+        if vertices.is_empty() {
+            return BoundingSphere { center: Vec3::new(0.0, 0.0, 0.0), radius: 0.0 };
+        }
+
+        let mut min = vertices[0].position;
+        let mut max = vertices[0].position;
+        for vertex in vertices {
+            min = min.inf(&vertex.position);
+            max = max.sup(&vertex.position);
+        }
+
+        let center = (min + max) * 0.5;
+        let radius = vertices.iter()
+            .map(|vertex| (vertex.position - center).magnitude())
+            .fold(0.0, f32::max);
+
+        BoundingSphere { center, radius }
+    }
+
+    pub fn transform(&self, matrix: &Mat4) -> Self {
+        let center = matrix * Vec4::new(self.center.x, self.center.y, self.center.z, 1.0);
+        let scale = (0..3)
+            .map(|column| matrix.fixed_slice::<3, 1>(0, column).magnitude())
+            .fold(0.0, f32::max);
+
+        BoundingSphere {
+            center: Vec3::new(center.x, center.y, center.z),
+            radius: self.radius * scale,
+        }
+    }
+
+    // Prueba la esfera (en espacio de mundo) contra los seis planos del frustum
+    // extraídos de la matriz proyección * vista.
+    pub fn is_visible(&self, view_projection: &Mat4) -> bool {
+        let row = |i: usize| view_projection.row(i).transpose();
+        let planes = [
+            row(3) + row(0),
+            row(3) - row(0),
+            row(3) + row(1),
+            row(3) - row(1),
+            row(3) + row(2),
+            row(3) - row(2),
+        ];
+
+        planes.iter().all(|plane| {
+            let normal = Vec3::new(plane.x, plane.y, plane.z);
+            let distance = (normal.dot(&self.center) + plane.w) / normal.magnitude();
+            distance >= -self.radius
+        })
+    }
+}
@@ -7,19 +7,24 @@ pub struct Fragment {
     pub color: Color,
     pub depth: f32,
     pub normal: Vec3,
-    pub intensity: f32,
+    // Luz que emite el fragmento (la escribe el shader)
+    pub emission: Color,
     pub vertex_position: Vec3,
+    pub world_position: Vec3,
     pub uv: Option<Vec2>,
+    // Cambio de uv entre píxeles vecinos, para elegir el nivel de mipmap
+    pub uv_footprint: f32,
 }
 
 impl Fragment {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         position: Vec2,
         color: Color,
         depth: f32,
         normal: Vec3,
-        intensity: f32,
         vertex_position: Vec3,
+        world_position: Vec3,
         uv: Option<Vec2>
     ) -> Self {  
         Fragment {
@@ -27,9 +32,11 @@ impl Fragment {
             color,
             depth,
             normal,
-            intensity,
+            emission: Color::black(),
             vertex_position,
-            uv
+            world_position,
+            uv,
+            uv_footprint: 0.0,
         }
     }
 }
@@ -1,14 +1,51 @@
-use std::f32::INFINITY;
 use std::fs::File;
 use std::io::{self, Write};
 use std::path::Path;
+use image::{ImageBuffer, ImageResult, Rgb};
+use rayon::prelude::*;
+use nalgebra_glm::{Vec2, Vec3};
+use crate::blend::BlendState;
+use crate::color::Color;
+use crate::tonemap::ToneMapping;
 
+// Atributos de la superficie visible en un píxel (G-buffer). La profundidad
+// está en `zbuffer`; el sombreado se hace después, una vez por píxel.
+#[derive(Clone, Copy, Debug)]
+pub struct GSample {
+    pub normal: Vec3,
+    pub vertex_position: Vec3,
+    pub world_position: Vec3,
+    pub uv: Vec2,
+    pub uv_footprint: f32,
+    pub material: u32,
+}
+
+pub const NO_MATERIAL: u32 = u32::MAX;
+
+impl Default for GSample {
+    fn default() -> Self {
+        GSample {
+            normal: Vec3::new(0.0, 0.0, 0.0),
+            vertex_position: Vec3::new(0.0, 0.0, 0.0),
+            world_position: Vec3::new(0.0, 0.0, 0.0),
+            uv: Vec2::new(0.0, 0.0),
+            uv_footprint: 0.0,
+            material: NO_MATERIAL,
+        }
+    }
+}
+
+// `hdr_buffer` es el destino del render (color en punto flotante); `buffer`
+// es la imagen que se presenta, escrita por `tone_map`. `emissive_buffer`
+// guarda la parte de `hdr_buffer` que emite luz propia.
 pub struct Framebuffer {
     pub width: usize,
     pub height: usize,
+    pub hdr_buffer: Vec<Color>,
     pub buffer: Vec<u32>,
     pub zbuffer: Vec<f32>,
-    pub emissive_buffer: Vec<u32>,
+    pub emissive_buffer: Vec<Color>,
+    pub gbuffer: Vec<GSample>,
     background_color: u32,
     current_color: u32,
 }
@@ -18,27 +55,32 @@ impl Framebuffer {
         Framebuffer {
             width,
             height,
+            hdr_buffer: vec![Color::black(); width * height],
             buffer: vec![0; width * height],
             zbuffer: vec![f32::INFINITY; width * height],
-            emissive_buffer: vec![0; width * height],
+            emissive_buffer: vec![Color::black(); width * height],
+            gbuffer: vec![GSample::default(); width * height],
             background_color: 0x000000, // Black
             current_color: 0xFFFFFF,   // White
         }
     }
 
     pub fn clear(&mut self) {
+        self.hdr_buffer.fill(Color::from_hex(self.background_color));
         self.buffer.fill(self.background_color);
-        self.zbuffer.fill(INFINITY);
-        self.emissive_buffer.fill(0);
+        self.zbuffer.fill(f32::INFINITY);
+        self.emissive_buffer.fill(Color::black());
+        self.gbuffer.fill(GSample::default());
     }
 
-    pub fn point(&mut self, x: usize, y: usize, depth: f32, emit: u32) {
+    pub fn point(&mut self, x: usize, y: usize, depth: f32, emit: Color) {
         if x < self.width && y < self.height {
             let index = y * self.width + x;
             if self.zbuffer[index] > depth {
-                self.buffer[index] = self.current_color;
+                self.hdr_buffer[index] = Color::from_hex(self.current_color) + emit;
                 self.zbuffer[index] = depth;
                 self.emissive_buffer[index] = emit;
+                self.gbuffer[index].material = NO_MATERIAL;
             }
         }
     }
@@ -50,28 +92,170 @@ impl Framebuffer {
     pub fn set_current_color(&mut self, color: u32) {
         self.current_color = color;
     }
-    pub fn set_emission(&mut self, emit: u32) {
+    pub fn set_emission(&mut self, emit: Color) {
         self.emissive_buffer.fill(emit);
     }
 
     // Set emission for a specific point in the framebuffer
-    pub fn set_emission_point(&mut self, x: usize, y: usize, emit: u32) {
+    pub fn set_emission_point(&mut self, x: usize, y: usize, emit: Color) {
         if x < self.width && y < self.height {
             let index = y * self.width + x;
             self.emissive_buffer[index] = emit;
         }
     }
+
+    // Divide el framebuffer en franjas horizontales de `rows` filas que se
+    // pueden escribir en paralelo.
+    pub fn bands_mut(&mut self, rows: usize) -> impl IndexedParallelIterator<Item = FramebufferBand<'_>> {
+        let width = self.width;
+        let chunk = width * rows;
+        self.hdr_buffer.par_chunks_mut(chunk)
+            .zip(self.zbuffer.par_chunks_mut(chunk))
+            .zip(self.emissive_buffer.par_chunks_mut(chunk))
+            .zip(self.gbuffer.par_chunks_mut(chunk))
+            .enumerate()
+            .map(move |(index, (((buffer, zbuffer), emissive_buffer), gbuffer))| FramebufferBand {
+                width,
+                y_start: index * rows,
+                buffer,
+                zbuffer,
+                emissive_buffer,
+                gbuffer,
+            })
+    }
+
+    // Divide el framebuffer en tiles de `size` x `size` (los del borde pueden
+    // ser más chicos), en orden de filas de tiles, para rasterizarlos en
+    // paralelo.
+    pub fn tiles_mut(&mut self, size: usize) -> Vec<FramebufferTile<'_>> {
+        let width = self.width;
+        let tiles_x = width.div_ceil(size);
+        split_tiles(&mut self.hdr_buffer, width, size).into_iter()
+            .zip(split_tiles(&mut self.zbuffer, width, size))
+            .zip(split_tiles(&mut self.emissive_buffer, width, size))
+            .zip(split_tiles(&mut self.gbuffer, width, size))
+            .enumerate()
+            .map(|(index, (((buffer, zbuffer), emissive_buffer), gbuffer))| FramebufferTile {
+                x_start: index % tiles_x * size,
+                y_start: index / tiles_x * size,
+                buffer,
+                zbuffer,
+                emissive_buffer,
+                gbuffer,
+            })
+            .collect()
+    }
+
+    // Convierte el color HDR en la imagen de salida.
+    pub fn tone_map(&mut self, tone_mapping: &ToneMapping) {
+        self.buffer.par_iter_mut()
+            .zip(self.hdr_buffer.par_iter())
+            .for_each(|(pixel, color)| *pixel = tone_mapping.map(*color).to_u32());
+    }
+}
+
+// Franja de filas completas del framebuffer; las coordenadas son las de
+// pantalla.
+pub struct FramebufferBand<'a> {
+    pub width: usize,
+    pub y_start: usize,
+    pub buffer: &'a mut [Color],
+    pub zbuffer: &'a mut [f32],
+    pub emissive_buffer: &'a mut [Color],
+    pub gbuffer: &'a mut [GSample],
+}
+
+// Parte un buffer de `width` columnas en tiles de `size` x `size`, en orden de
+// filas de tiles. Cada tile es la lista de sus pedazos de fila.
+pub fn split_tiles<T>(buffer: &mut [T], width: usize, size: usize) -> Vec<Vec<&mut [T]>> {
+    let tiles_x = width.div_ceil(size);
+    let mut tiles: Vec<Vec<&mut [T]>> = Vec::new();
+    for band in buffer.chunks_mut(width * size) {
+        let first = tiles.len();
+        tiles.extend((0..tiles_x).map(|_| Vec::with_capacity(size)));
+        for row in band.chunks_mut(width) {
+            for (tile, part) in tiles[first..].iter_mut().zip(row.chunks_mut(size)) {
+                tile.push(part);
+            }
+        }
+    }
+    tiles
+}
+
+// Región rectangular y disjunta del framebuffer; las coordenadas son las de
+// pantalla.
+pub struct FramebufferTile<'a> {
+    pub x_start: usize,
+    pub y_start: usize,
+    pub buffer: Vec<&'a mut [Color]>,
+    pub zbuffer: Vec<&'a mut [f32]>,
+    pub emissive_buffer: Vec<&'a mut [Color]>,
+    pub gbuffer: Vec<&'a mut [GSample]>,
 }
 
-// Add trait implementation for saving to BMP file
+impl FramebufferTile<'_> {
+    pub fn width(&self) -> usize {
+        self.buffer.first().map_or(0, |row| row.len())
+    }
+
+    pub fn height(&self) -> usize {
+        self.buffer.len()
+    }
+
+    // Fila y columna dentro del tile.
+    fn index(&self, x: usize, y: usize) -> Option<(usize, usize)> {
+        let (column, row) = (x.checked_sub(self.x_start)?, y.checked_sub(self.y_start)?);
+        (column < self.width() && row < self.height()).then_some((row, column))
+    }
+
+    // `emit` se suma al color y queda guardado en el buffer emisivo.
+    pub fn point(&mut self, x: usize, y: usize, color: Color, depth: f32, emit: Color) {
+        if let Some((row, column)) = self.index(x, y) {
+            if self.zbuffer[row][column] > depth {
+                self.buffer[row][column] = color + emit;
+                self.zbuffer[row][column] = depth;
+                self.emissive_buffer[row][column] = emit;
+                self.gbuffer[row][column].material = NO_MATERIAL;
+            }
+        }
+    }
+
+    // Mezcla un fragmento transparente si pasa la prueba de profundidad. La
+    // emisión se suma encima, atenuada por la opacidad del fragmento.
+    pub fn blend(&mut self, x: usize, y: usize, color: Color, emit: Color, depth: f32, state: &BlendState) {
+        if let Some((row, column)) = self.index(x, y) {
+            if self.zbuffer[row][column] > depth {
+                let emit = emit * color.a;
+                self.buffer[row][column] = state.apply(self.buffer[row][column], color) + emit;
+                self.emissive_buffer[row][column] = self.emissive_buffer[row][column] + emit;
+                if state.depth_write {
+                    self.zbuffer[row][column] = depth;
+                }
+            }
+        }
+    }
+
+    // Escribe una muestra del G-buffer si pasa la prueba de profundidad.
+    pub fn sample(&mut self, x: usize, y: usize, depth: f32, sample: GSample) {
+        if let Some((row, column)) = self.index(x, y) {
+            if self.zbuffer[row][column] > depth {
+                self.zbuffer[row][column] = depth;
+                self.gbuffer[row][column] = sample;
+            }
+        }
+    }
+}
+
+// Add trait implementation for saving to BMP and PNG files
 pub trait RenderableToFile {
     fn render_buffer(&self, filename: &str) -> io::Result<()>;
+    fn render_png(&self, filename: &str) -> ImageResult<()>;
 }
 
 impl RenderableToFile for Framebuffer {
     fn render_buffer(&self, filename: &str) -> io::Result<()> {
         let path = Path::new(filename);
-        let mut file = File::create(&path)?;
+        let mut file = File::create(path)?;
 
         // Write BMP header
         let file_size = 14 + 40 + (self.width * self.height * 4) as u32;
@@ -116,5 +300,12 @@ impl RenderableToFile for Framebuffer {
 
         Ok(())
     }
-}
 
+    fn render_png(&self, filename: &str) -> ImageResult<()> {
+        let image = ImageBuffer::from_fn(self.width as u32, self.height as u32, |x, y| {
+            let color = self.buffer[y as usize * self.width + x as usize];
+            Rgb([(color >> 16) as u8, (color >> 8) as u8, color as u8])
+        });
+        image.save(filename)
+    }
+}
@@ -0,0 +1,25 @@
+pub mod pov;
+pub mod color;
+pub mod blend;
+pub mod tonemap;
+pub mod bloom;
+pub mod post;
+pub mod fragment;
+pub mod framebuffer;
+pub mod line;
+pub mod clipping;
+pub mod culling;
+pub mod obj;
+pub mod render;
+pub mod shader;
+pub mod vertex;
+pub mod noise;
+pub mod texture;
+pub mod material;
+pub mod light;
+pub mod shadow;
+pub mod atmosphere;
+pub mod rings;
+pub mod background;
+pub mod scene;
+pub mod scene_graph;
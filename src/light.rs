@@ -0,0 +1,100 @@
+use nalgebra_glm::Vec3;
+use crate::render::Uniforms;
+
+// Fuentes de luz de la escena, en espacio de mundo. Todos los shaders las
+// leen de `uniforms.lights` a través de las funciones de este módulo, que
+// también aplican el mapa de sombras.
+#[derive(Clone, Copy, Debug)]
+pub enum Light {
+    Ambient {
+        color: Vec3,
+        intensity: f32,
+    },
+    // `direction` es hacia donde viaja la luz.
+    Directional {
+        direction: Vec3,
+        color: Vec3,
+        intensity: f32,
+    },
+    // La intensidad cae como 1 / (1 + falloff * d²).
+    Point {
+        position: Vec3,
+        color: Vec3,
+        intensity: f32,
+        falloff: f32,
+    },
+}
+
+impl Light {
+    // Dirección hacia la luz y radiancia que llega a `position`. La luz
+    // ambiental no tiene dirección.
+    pub fn incident(&self, position: &Vec3) -> Option<(Vec3, Vec3)> {
+        match *self {
+            Light::Ambient { .. } => None,
+            Light::Directional { direction, color, intensity } => {
+                Some((-direction.normalize(), color * intensity))
+            }
+            Light::Point { position: light_position, color, intensity, falloff } => {
+                let to_light = light_position - position;
+                let distance_squared = to_light.magnitude_squared();
+                let attenuation = 1.0 / (1.0 + falloff * distance_squared);
+                Some((to_light.normalize(), color * (intensity * attenuation)))
+            }
+        }
+    }
+}
+
+// Luz que emite un cuerpo de la escena (por ejemplo el sol); se convierte en
+// una luz puntual en la posición del cuerpo en cada frame.
+#[derive(Clone, Copy, Debug)]
+pub struct Emitter {
+    pub color: Vec3,
+    pub intensity: f32,
+    pub falloff: f32,
+}
+
+impl Emitter {
+    pub fn at(&self, position: Vec3) -> Light {
+        Light::Point {
+            position,
+            color: self.color,
+            intensity: self.intensity,
+            falloff: self.falloff,
+        }
+    }
+}
+
+pub fn ambient_light(lights: &[Light]) -> Vec3 {
+    lights.iter().fold(Vec3::zeros(), |sum, light| match light {
+        Light::Ambient { color, intensity } => sum + color * *intensity,
+        _ => sum,
+    })
+}
+
+// Dirección y radiancia de cada luz que llega a `position`, ya atenuada por
+// el mapa de sombras si la luz tiene uno y por los anillos que se interpongan.
+pub fn light_contributions<'a>(uniforms: &'a Uniforms, position: &'a Vec3, normal: &'a Vec3) -> impl Iterator<Item = (Vec3, Vec3)> + 'a {
+    uniforms.lights.iter().enumerate().filter_map(move |(index, light)| {
+        let (direction, radiance) = light.incident(position)?;
+        let distance = match light {
+            Light::Point { position: light_position, .. } => (light_position - position).magnitude(),
+            _ => f32::INFINITY,
+        };
+        let radiance = uniforms.rings.iter()
+            .fold(radiance, |radiance, rings| radiance * rings.transmittance(position, &direction, distance));
+        match &uniforms.shadow_map {
+            Some(shadow_map) if shadow_map.light == index && normal.dot(&direction) > 0.0 => {
+                Some((direction, radiance * shadow_map.visibility(position, normal)))
+            }
+            _ => Some((direction, radiance)),
+        }
+    })
+}
+
+// Luz ambiental más la difusa (Lambert) de cada fuente, por canal.
+pub fn diffuse_lighting(uniforms: &Uniforms, position: &Vec3, normal: &Vec3) -> Vec3 {
+    light_contributions(uniforms, position, normal)
+        .fold(ambient_light(&uniforms.lights), |sum, (direction, radiance)| {
+            sum + radiance * normal.dot(&direction).max(0.0)
+        })
+}
@@ -1,8 +1,8 @@
-use nalgebra_glm::{dot, Vec2, Vec3};
+use nalgebra_glm::{Vec2, Vec3};
 use crate::color::Color;
 use crate::fragment::Fragment;
 use crate::vertex::Vertex;
-use crate::shader::fragment_shader;
+use crate::shader::FragmentShader;
 use crate::render::Uniforms;
 
 pub fn line(v1: &Vertex, v2: &Vertex) -> Vec<Fragment> {
@@ -26,21 +26,27 @@ pub fn line(v1: &Vertex, v2: &Vertex) -> Vec<Fragment> {
     let tex_coords_end = v2.tex_coords;
 
     for i in 0..=steps {
-        let t = i as f32 / steps as f32;
+        let screen_t = i as f32 / steps as f32;
 
         let position = a + step * i as f32;
-        let depth = v1.transformed_position.z + z_diff * t;
+        let depth = v1.transformed_position.z + z_diff * screen_t;
+
+        // Corrección de perspectiva: los atributos se interpolan con 1/w
+        let start_weight = (1.0 - screen_t) / v1.clip_position.w;
+        let end_weight = screen_t / v2.clip_position.w;
+        let t = end_weight / (start_weight + end_weight);
         let color = color_start.lerp(&color_end, t);
         let normal = (normal_start * (1.0 - t) + normal_end * t).normalize();
         let tex_coords = tex_coords_start * (1.0 - t) + tex_coords_end * t;
+        let world_position = v1.world_position * (1.0 - t) + v2.world_position * t;
 
         fragments.push(Fragment::new(
             position,
             color,
             depth,
             normal,
-            0.0, 
             Vec3::new(position.x, position.y, depth),
+            world_position,
             Some(tex_coords),
         ));
     }
@@ -58,7 +64,29 @@ pub fn triangle_wireframe(v1: &Vertex, v2: &Vertex, v3: &Vertex) -> Vec<Fragment
     fragments
 }
 
-pub fn triangle_flat_shade(v1: &Vertex, v2: &Vertex, v3: &Vertex, uniforms: &Uniforms, time: u32) -> Vec<Fragment> {
+// Rectángulo de píxeles (inclusivo) al que se limita la rasterización.
+#[derive(Clone, Copy, Debug)]
+pub struct ScreenRect {
+    pub min_x: i32,
+    pub min_y: i32,
+    pub max_x: i32,
+    pub max_y: i32,
+}
+
+pub fn triangle_flat_shade(v1: &Vertex, v2: &Vertex, v3: &Vertex, uniforms: &Uniforms, shader: &dyn FragmentShader, time: u32, rect: &ScreenRect) -> Vec<Fragment> {
+    let mut fragments = triangle_fragments(v1, v2, v3, rect);
+
+    for fragment in fragments.iter_mut() {
+        let (color, emission) = shader.shade(fragment, uniforms, time);
+        fragment.color = color; // Color dinámico del shader
+        fragment.emission = emission;
+    }
+
+    fragments
+}
+
+// Rasteriza el triángulo e interpola sus atributos, sin sombrear.
+pub fn triangle_fragments(v1: &Vertex, v2: &Vertex, v3: &Vertex, rect: &ScreenRect) -> Vec<Fragment> {
     let mut fragments = Vec::new();
 
     let (a, b, c) = (
@@ -68,52 +96,57 @@ pub fn triangle_flat_shade(v1: &Vertex, v2: &Vertex, v3: &Vertex, uniforms: &Uni
     );
 
     let (min_x, min_y, max_x, max_y) = calculate_bounding_box(&a, &b, &c);
+    let (min_x, min_y) = (min_x.max(rect.min_x), min_y.max(rect.min_y));
+    let (max_x, max_y) = (max_x.min(rect.max_x), max_y.min(rect.max_y));
     let triangle_area = edge_function(&a, &b, &c);
 
+    // Coordenadas de textura en cualquier punto de pantalla (para el footprint)
+    let tex_coords_at = |px: f32, py: f32| {
+        let (w1, w2, w3) = barycentric_coordinates(&Vec3::new(px, py, 0.0), &a, &b, &c, triangle_area);
+        let (w1, w2, w3) = perspective_correct(w1, w2, w3, v1.clip_position.w, v2.clip_position.w, v3.clip_position.w);
+        v1.tex_coords * w1 + v2.tex_coords * w2 + v3.tex_coords * w3
+    };
+
     for y in min_y..=max_y {
         for x in min_x..=max_x {
             let point = Vec3::new(x as f32 + 0.5, y as f32 + 0.5, 0.0);
             let (w1, w2, w3) = barycentric_coordinates(&point, &a, &b, &c, triangle_area);
 
             if w1 >= 0.0 && w2 >= 0.0 && w3 >= 0.0 {
+                // Interpolación de la profundidad (lineal en pantalla)
+                let depth = a.z * w1 + b.z * w2 + c.z * w3;
+
+                // El resto de atributos se interpolan con corrección de perspectiva
+                let (w1, w2, w3) = perspective_correct(w1, w2, w3, v1.clip_position.w, v2.clip_position.w, v3.clip_position.w);
+
                 // Interpolación de la normal
                 let normal = (v1.transformed_normal * w1
                     + v2.transformed_normal * w2
                     + v3.transformed_normal * w3)
                     .normalize();
 
-                // Interpolación de la profundidad
-                let depth = a.z * w1 + b.z * w2 + c.z * w3;
-
                 // Interpolación de las coordenadas de textura
                 let tex_coords = v1.tex_coords * w1 + v2.tex_coords * w2 + v3.tex_coords * w3;
+                let uv_footprint = (tex_coords_at(x as f32 + 1.5, y as f32 + 0.5) - tex_coords).norm()
+                    .max((tex_coords_at(x as f32 + 0.5, y as f32 + 1.5) - tex_coords).norm());
 
                 // Interpolación de la posición original del vértice
                 let vertex_position = v1.position * w1 + v2.position * w2 + v3.position * w3;
 
-                // Crear el fragmento temporal
-                let fragment = Fragment::new(
-                    Vec2::new(point.x, point.y),
-                    Color::new(0, 0, 0), // Se sobrescribirá por el shader
-                    depth,
-                    normal,
-                    0.0,
-                    vertex_position,
-                    Some(tex_coords),
-                );
-
-                let (color, _emit) = fragment_shader(&fragment, uniforms, time);
+                // Interpolación de la posición en espacio de mundo (para la iluminación)
+                let world_position = v1.world_position * w1 + v2.world_position * w2 + v3.world_position * w3;
 
-                // Crear el fragmento final con el color calculado por el shader
-                fragments.push(Fragment::new(
+                let mut fragment = Fragment::new(
                     Vec2::new(point.x, point.y),
-                    color, // Color dinámico del shader
+                    Color::new(0, 0, 0), // Se sobrescribe al sombrear
                     depth,
                     normal,
-                    0.0,
                     vertex_position,
+                    world_position,
                     Some(tex_coords),
-                ));
+                );
+                fragment.uv_footprint = uv_footprint;
+                fragments.push(fragment);
             }
         }
     }
@@ -122,7 +155,7 @@ pub fn triangle_flat_shade(v1: &Vertex, v2: &Vertex, v3: &Vertex, uniforms: &Uni
 }
 
 
-fn calculate_bounding_box(v1: &Vec3, v2: &Vec3, v3: &Vec3) -> (i32, i32, i32, i32) {
+pub fn calculate_bounding_box(v1: &Vec3, v2: &Vec3, v3: &Vec3) -> (i32, i32, i32, i32) {
     let min_x = v1.x.min(v2.x).min(v3.x).floor() as i32;
     let min_y = v1.y.min(v2.y).min(v3.y).floor() as i32;
     let max_x = v1.x.max(v2.x).max(v3.x).ceil() as i32;
@@ -147,3 +180,9 @@ fn barycentric_coordinates(
 fn edge_function(a: &Vec3, b: &Vec3, c: &Vec3) -> f32 {
     (c.x - a.x) * (b.y - a.y) - (c.y - a.y) * (b.x - a.x)
 }
+
+fn perspective_correct(w1: f32, w2: f32, w3: f32, clip_w1: f32, clip_w2: f32, clip_w3: f32) -> (f32, f32, f32) {
+    let (p1, p2, p3) = (w1 / clip_w1, w2 / clip_w2, w3 / clip_w3);
+    let sum = p1 + p2 + p3;
+    (p1 / sum, p2 / sum, p3 / sum)
+}
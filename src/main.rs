@@ -1,31 +1,27 @@
-mod pov;
-mod color;
-mod fragment;
-mod framebuffer;
-mod line;
-mod obj;
-mod render;
-mod shader;
-mod vertex;
-mod noise;
-
-use crate::pov::POV;
-use crate::obj::Obj;
+use lab04::pov::POV;
+use lab04::scene::Scene;
+use lab04::shader::ShaderRegistry;
 use minifb::{Window, WindowOptions, Key};
-use nalgebra_glm::Vec3;
+use std::env;
 use std::time::Duration;
 use std::f32::consts::PI;
-use crate::framebuffer::Framebuffer;
-use crate::render::{create_model_matrix, create_perspective_matrix, create_view_matrix, create_viewport_matrix, render, Uniforms, gaussian_blur, apply_bloom};
-use fastnoise_lite::FastNoiseLite;
-use crate::noise::{create_noise, create_cloud_noise};
+use lab04::framebuffer::Framebuffer;
+use lab04::render::{create_perspective_matrix, create_view_matrix, create_viewport_matrix, Uniforms};
 
-pub fn start() {
+const SYSTEM_KEYS: [Key; 9] = [
+    Key::Key1, Key::Key2, Key::Key3, Key::Key4, Key::Key5,
+    Key::Key6, Key::Key7, Key::Key8, Key::Key9,
+];
+
+pub fn start(scene_path: &str) {
     let window_width = 600;
     let window_height = 600;
     let framebuffer_width = window_width;
     let framebuffer_height = window_height;
 
+    let registry = ShaderRegistry::with_builtin();
+    let mut scene = Scene::load(scene_path, &registry).unwrap_or_else(|error| panic!("Failed to load scene {}: {}", scene_path, error));
+
     let frame_delay = Duration::from_millis(16);
     let mut framebuffer = Framebuffer::new(window_width, window_height);
     let mut window = Window::new(
@@ -36,34 +32,13 @@ pub fn start() {
     )
     .unwrap();
 
-    let mut pov = POV::new(
-        Vec3::new(5.0, 5.0, 0.0),
-        Vec3::new(0.0, 0.0, 0.0),
-        Vec3::new(0.0, 1.0, 0.0),
-    );
-
-    framebuffer.set_background_color(120);
+    let mut pov = POV::new(scene.camera.eye, scene.camera.center, scene.camera.up);
 
-    let translation = Vec3::new(0.0, 0.0, 0.0);
-    let rotation = Vec3::new(0.0, 0.0, 0.0);
-    let scale =1.0f32;
-
-    let obj = Obj::load_custom_obj("src/3D/sphere.obj").expect("Failed to load obj");
-    let vertex_array = obj.get_vertex_array();
+    framebuffer.set_background_color(scene.camera.background);
 
     let mut time = 0;
-    let mut current_shader = 1;
-    let mut current_noise = (
-        create_noise(0),
-        create_noise(1),
-        create_noise(3),
-        create_noise(4),
-        create_noise(5),
-        create_noise(6),
-        create_noise(7),
-    );
-
-    let model_matrix = create_model_matrix(translation, scale, rotation);
+    let mut current_system = 0;
+
     let mut view_matrix = create_view_matrix(pov.eye, pov.center, pov.up);
     let projection_matrix = create_perspective_matrix(window_width as f32, window_height as f32);
     let viewport_matrix = create_viewport_matrix(framebuffer_width as f32, framebuffer_height as f32);
@@ -76,15 +51,13 @@ pub fn start() {
 
         let keys = window.get_keys_pressed(minifb::KeyRepeat::No);
         for key in keys {
-            match key {
-                Key::Key1 => {current_shader = 1; current_noise.0 = create_noise(1)},
-                Key::Key2 => {current_shader = 3; current_noise.1 = create_noise(2)},
-                Key::Key3 => {current_shader = 4; current_noise.2 = create_noise(3)},
-                Key::Key4 => {current_shader = 8; current_noise.3 = create_noise(4)},
-                Key::Key5 => {current_shader = 5; current_noise.4 = create_noise(5)},
-                Key::Key6 => {current_shader = 6; current_noise.5 = create_noise(6)},
-                Key::Key7 => {current_shader = 7; current_noise.6 = create_noise(7)},
-                _ => {}
+            if key == Key::B {
+                scene.post.toggle("bloom");
+            }
+            if let Some(index) = SYSTEM_KEYS.iter().position(|&k| k == key) {
+                if index < scene.systems.len() {
+                    current_system = index;
+                }
             }
         }
 
@@ -95,64 +68,11 @@ pub fn start() {
 
         framebuffer.clear();
 
-        let mut uniforms = Uniforms {
-            model_matrix,
-            view_matrix,
-            projection_matrix,
-            viewport_matrix,
-            time,
-            noise: create_noise(1),
-            cloud_noise: create_cloud_noise(),
-            band_noise: FastNoiseLite::new(),
-            current_shader,
-        };
-
-        if current_shader == 1 {
-            uniforms.current_shader = 1;
-            uniforms.model_matrix = create_model_matrix(translation, scale, rotation);
-            render(&mut framebuffer, &uniforms, &vertex_array, time);
-            let moon_angle = time as f32 * 0.02;
-            let moon_translation = Vec3::new(
-                3.0 * moon_angle.cos(),
-                0.0,
-                3.0 * moon_angle.sin(),
-            );
-            let moon_angle_2 = time as f32 * 0.015;
-            let moon_translation_2 = Vec3::new(
-                5.0 * moon_angle_2.cos(),
-                3.0,
-                5.0 * moon_angle_2.sin(),
-            );
-            uniforms.current_shader = 2;
-            uniforms.model_matrix = create_model_matrix(moon_translation_2, 0.3, Vec3::new(0.0, 0.0, 0.0)); 
-            render(&mut framebuffer, &uniforms, &vertex_array, time);
-            uniforms.model_matrix = create_model_matrix(moon_translation, 0.5, Vec3::new(0.0, 0.0, 0.0));
-            render(&mut framebuffer, &uniforms, &vertex_array, time);
-        } else if current_shader == 3 {
-            uniforms.current_shader = 3;
-            uniforms.model_matrix = create_model_matrix(translation, scale * 1.75, rotation);
-            render(&mut framebuffer, &uniforms, &vertex_array, time);
-        } else if current_shader == 4 {
-            uniforms.current_shader = 4;
-            uniforms.model_matrix = create_model_matrix(translation, scale * 2.0, rotation);
-            render(&mut framebuffer, &uniforms, &vertex_array, time);
-        } else if current_shader == 5 {
-            uniforms.current_shader = 5;
-            uniforms.model_matrix = create_model_matrix(translation, scale * 4.25, rotation);
-            render(&mut framebuffer, &uniforms, &vertex_array, time);
-        } else if current_shader == 6 {
-            uniforms.current_shader = 6;
-            uniforms.model_matrix = create_model_matrix(translation, scale * 2.0 , rotation);
-            render(&mut framebuffer, &uniforms, &vertex_array, time);
-        } else if current_shader == 7 {
-            uniforms.current_shader = 7;
-            uniforms.model_matrix = create_model_matrix(translation, scale, rotation);
-            render(&mut framebuffer, &uniforms, &vertex_array, time as u32);
-        } else if current_shader == 8 {
-            uniforms.current_shader = 8;
-            uniforms.model_matrix = create_model_matrix(translation, scale * 2.0, rotation);
-            render(&mut framebuffer, &uniforms, &vertex_array, time);
-        }
+        let uniforms = Uniforms::new(view_matrix, projection_matrix, viewport_matrix, time);
+
+        scene.render_system(&mut framebuffer, &uniforms, current_system, time);
+        scene.post.apply(&mut framebuffer, time);
+        framebuffer.tone_map(&scene.tone_mapping);
 
         time += 1;
 
@@ -190,6 +110,6 @@ fn handle_input(window: &Window, pov: &mut POV) {
 }
 
 fn main() {
-    start();
+    let scene_path = env::args().nth(1).unwrap_or_else(|| "scenes/default.toml".to_string());
+    start(&scene_path);
 }
-
@@ -0,0 +1,135 @@
+use std::f32::consts::PI;
+use std::sync::Arc;
+use nalgebra_glm::Vec3;
+use serde::Deserialize;
+use crate::color::Color;
+use crate::fragment::Fragment;
+use crate::light::{ambient_light, light_contributions};
+use crate::render::Uniforms;
+use crate::texture::Texture;
+
+// Modelo de iluminación de la superficie; se elige por cuerpo en la escena.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LightingModel {
+    // Solo difusa
+    #[default]
+    Lambert,
+    // Difusa más brillo especular con el exponente `shininess` (Ns del MTL)
+    BlinnPhong,
+    // PBR con metalicidad y rugosidad
+    CookTorrance,
+}
+
+// Propiedades de superficie de una parte de la malla, normalmente leídas del
+// archivo MTL que acompaña al OBJ (Kd, Ks, Ns y map_Kd).
+#[derive(Clone)]
+pub struct SurfaceMaterial {
+    pub model: LightingModel,
+    pub diffuse: Vec3,
+    pub specular: Vec3,
+    pub shininess: f32,
+    pub metallic: f32,
+    pub roughness: f32,
+    pub diffuse_texture: Option<Arc<Texture>>,
+}
+
+impl Default for SurfaceMaterial {
+    fn default() -> Self {
+        SurfaceMaterial {
+            model: LightingModel::default(),
+            diffuse: Vec3::new(1.0, 1.0, 1.0),
+            specular: Vec3::new(0.0, 0.0, 0.0),
+            shininess: 0.0,
+            metallic: 0.0,
+            roughness: 0.5,
+            diffuse_texture: None,
+        }
+    }
+}
+
+// Cambios al material de la malla definidos por el cuerpo en la escena; los
+// campos vacíos conservan el valor del MTL.
+#[derive(Clone, Copy, Debug, Default, Deserialize)]
+pub struct MaterialSettings {
+    pub model: Option<LightingModel>,
+    pub specular: Option<[f32; 3]>,
+    pub shininess: Option<f32>,
+    pub metallic: Option<f32>,
+    pub roughness: Option<f32>,
+}
+
+impl MaterialSettings {
+    pub fn apply(&self, material: &SurfaceMaterial) -> SurfaceMaterial {
+        SurfaceMaterial {
+            model: self.model.unwrap_or(material.model),
+            specular: self.specular.map(Vec3::from).unwrap_or(material.specular),
+            shininess: self.shininess.unwrap_or(material.shininess),
+            metallic: self.metallic.unwrap_or(material.metallic),
+            roughness: self.roughness.unwrap_or(material.roughness),
+            ..material.clone()
+        }
+    }
+}
+
+// Ilumina el color base del shader con el modelo del material del cuerpo.
+// `specular_mask` escala el brillo especular (por ejemplo, solo en el agua).
+pub fn shade_surface(uniforms: &Uniforms, fragment: &Fragment, albedo: Color, specular_mask: f32) -> Color {
+    let albedo = Vec3::new(albedo.r, albedo.g, albedo.b);
+    let color = surface_radiance(uniforms, fragment, albedo, specular_mask);
+    Color::from_float(color.x, color.y, color.z)
+}
+
+pub fn surface_radiance(uniforms: &Uniforms, fragment: &Fragment, albedo: Vec3, specular_mask: f32) -> Vec3 {
+    let material = &uniforms.material;
+    let position = fragment.world_position;
+    let normal = fragment.normal.normalize();
+    let view = (uniforms.camera_position - position).normalize();
+
+    let mut color = albedo.component_mul(&ambient_light(&uniforms.lights));
+    for (light, radiance) in light_contributions(uniforms, &position, &normal) {
+        let n_dot_l = normal.dot(&light);
+        if n_dot_l <= 0.0 {
+            continue;
+        }
+        let reflected = match material.model {
+            LightingModel::Lambert => albedo,
+            LightingModel::BlinnPhong => {
+                let half = (light + view).normalize();
+                let highlight = normal.dot(&half).max(0.0).powf(material.shininess.max(1.0));
+                albedo + material.specular * (highlight * specular_mask)
+            }
+            LightingModel::CookTorrance => cook_torrance(material, albedo, &normal, &view, &light, specular_mask),
+        };
+        color += reflected.component_mul(&radiance) * n_dot_l;
+    }
+
+    color
+}
+
+// BRDF de Cook-Torrance (GGX, Smith y Fresnel de Schlick). Está multiplicada
+// por π para que la parte difusa coincida con la de Lambert de los demás modelos.
+fn cook_torrance(material: &SurfaceMaterial, albedo: Vec3, normal: &Vec3, view: &Vec3, light: &Vec3, specular_mask: f32) -> Vec3 {
+    let roughness = material.roughness.clamp(0.04, 1.0);
+    let metallic = material.metallic.clamp(0.0, 1.0);
+    let half = (light + view).normalize();
+    let n_dot_l = normal.dot(light).max(1e-4);
+    let n_dot_v = normal.dot(view).max(1e-4);
+    let n_dot_h = normal.dot(&half).max(0.0);
+    let v_dot_h = view.dot(&half).max(0.0);
+
+    let alpha = roughness * roughness;
+    let alpha2 = alpha * alpha;
+    let denominator = n_dot_h * n_dot_h * (alpha2 - 1.0) + 1.0;
+    let distribution = alpha2 / (PI * denominator * denominator);
+
+    let k = (roughness + 1.0) * (roughness + 1.0) / 8.0;
+    let geometry = (n_dot_l / (n_dot_l * (1.0 - k) + k)) * (n_dot_v / (n_dot_v * (1.0 - k) + k));
+
+    let f0 = Vec3::new(0.04, 0.04, 0.04).lerp(&albedo, metallic);
+    let fresnel = f0 + (Vec3::new(1.0, 1.0, 1.0) - f0) * (1.0 - v_dot_h).powi(5);
+
+    let specular = fresnel * (distribution * geometry / (4.0 * n_dot_l * n_dot_v));
+    let diffuse = (Vec3::new(1.0, 1.0, 1.0) - fresnel).component_mul(&albedo) * (1.0 - metallic);
+    diffuse + specular * (PI * specular_mask)
+}
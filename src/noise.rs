@@ -1,92 +1,166 @@
 use fastnoise_lite::{FastNoiseLite, NoiseType, FractalType};
 
-pub fn create_noise(current_shader: u8) -> FastNoiseLite {
-    match current_shader {
-        1 => create_kenshi_noise(),
-        2 => create_ratchet_t_noise(),
-        3 => create_rocky_noise(),
-        4 => FastNoiseLite::new(),
-        5 => create_ratchet_noise(),
-        6 => create_simple_noise(), 
-        8 => create_moon_noise(),
-        9 => FastNoiseLite::new(),
-        _ => create_kenshi_noise(),  
-    }
-}
-pub fn create_cloud_noise() -> FastNoiseLite {
-    let mut noise = FastNoiseLite::with_seed(805);  
-    noise.set_noise_type(Some(NoiseType::Perlin)); 
-    noise.set_fractal_type(Some(FractalType::FBm));
-    noise.set_fractal_octaves(Some(2)); 
-    noise.set_fractal_lacunarity(Some(3.0));
-    noise.set_fractal_gain(Some(0.5));
-    noise.set_frequency(Some(0.01)); 
-    noise
+// Parámetros de un ruido. `None` deja el valor por defecto de FastNoiseLite.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NoiseSettings {
+    pub seed: Option<i32>,
+    pub noise_type: Option<NoiseType>,
+    pub fractal_type: Option<FractalType>,
+    pub octaves: Option<i32>,
+    pub lacunarity: Option<f32>,
+    pub gain: Option<f32>,
+    pub frequency: Option<f32>,
 }
 
-fn create_kenshi_noise() -> FastNoiseLite {
-    let mut noise = FastNoiseLite::with_seed(623);
-    noise.set_noise_type(Some(NoiseType::OpenSimplex2S));
-    noise.set_fractal_type(Some(FractalType::Ridged));
-    noise.set_fractal_octaves(Some(5));
-    noise.set_fractal_lacunarity(Some(2.0));
-    noise.set_fractal_gain(Some(0.5));
-    noise.set_frequency(Some(0.8));
-    noise
-}
+impl NoiseSettings {
+    pub fn create(&self) -> FastNoiseLite {
+        let mut noise = FastNoiseLite::new();
+        noise.set_seed(self.seed);
+        noise.set_noise_type(self.noise_type);
+        noise.set_fractal_type(self.fractal_type);
+        noise.set_fractal_octaves(self.octaves);
+        noise.set_fractal_lacunarity(self.lacunarity);
+        noise.set_fractal_gain(self.gain);
+        noise.set_frequency(self.frequency);
+        noise
+    }
 
-fn create_ratchet_t_noise() -> FastNoiseLite {
-    let mut noise = FastNoiseLite::with_seed(3344);
-    noise.set_noise_type(Some(NoiseType::Perlin));
-    noise.set_fractal_type(Some(FractalType::Ridged));
-    noise.set_fractal_octaves(Some(8));
-    noise.set_fractal_lacunarity(Some(3.0));
-    noise.set_fractal_gain(Some(0.7));
-    noise.set_frequency(Some(1.8));
-    noise
+    pub fn preset(name: &str) -> Option<Self> {
+        match name {
+            "kenshi" => Some(KENSHI_NOISE),
+            "ratchet_toxic" => Some(RATCHET_T_NOISE),
+            "rocky" => Some(ROCKY_NOISE),
+            "ratchet" => Some(RATCHET_NOISE),
+            "simple" => Some(SIMPLE_NOISE),
+            "moon" => Some(MOON_NOISE),
+            "cloud" => Some(CLOUD_NOISE),
+            "ring" => Some(RING_NOISE),
+            "plain" => Some(NoiseSettings::default()),
+            _ => None,
+        }
+    }
 }
 
-pub fn create_moon_noise() -> FastNoiseLite {
-    let mut noise = FastNoiseLite::with_seed(4321);
-    noise.set_noise_type(Some(NoiseType::OpenSimplex2));
-    noise.set_fractal_type(Some(FractalType::PingPong));
-    noise.set_fractal_octaves(Some(2));
-    noise.set_fractal_lacunarity(Some(2.0));
-    noise.set_fractal_gain(Some(0.5));
-    noise.set_frequency(Some(3.0));
-    noise
+pub fn parse_noise_type(name: &str) -> Option<NoiseType> {
+    match name {
+        "OpenSimplex2" => Some(NoiseType::OpenSimplex2),
+        "OpenSimplex2S" => Some(NoiseType::OpenSimplex2S),
+        "Cellular" => Some(NoiseType::Cellular),
+        "Perlin" => Some(NoiseType::Perlin),
+        "ValueCubic" => Some(NoiseType::ValueCubic),
+        "Value" => Some(NoiseType::Value),
+        _ => None,
+    }
 }
 
-fn create_rocky_noise() -> FastNoiseLite {
-    let mut noise = FastNoiseLite::with_seed(4321);
-    noise.set_noise_type(Some(NoiseType::Perlin));
-    noise.set_fractal_type(Some(FractalType::PingPong));
-    noise.set_fractal_octaves(Some(5));
-    noise.set_fractal_lacunarity(Some(2.0));
-    noise.set_fractal_gain(Some(1.0));
-    noise.set_frequency(Some(5.0));
-    noise
+pub fn parse_fractal_type(name: &str) -> Option<FractalType> {
+    match name {
+        "None" => Some(FractalType::None),
+        "FBm" => Some(FractalType::FBm),
+        "Ridged" => Some(FractalType::Ridged),
+        "PingPong" => Some(FractalType::PingPong),
+        "DomainWarpProgressive" => Some(FractalType::DomainWarpProgressive),
+        "DomainWarpIndependent" => Some(FractalType::DomainWarpIndependent),
+        _ => None,
+    }
 }
 
-fn create_ratchet_noise() -> FastNoiseLite {
-    let mut noise = FastNoiseLite::with_seed(9876);
-    noise.set_noise_type(Some(NoiseType::OpenSimplex2));
-    noise.set_fractal_type(Some(FractalType::DomainWarpProgressive));
-    noise.set_fractal_octaves(Some(6));
-    noise.set_fractal_lacunarity(Some(2.0));
-    noise.set_fractal_gain(Some(0.5));
-    noise.set_frequency(Some(2.0));
-    noise
+pub fn create_cloud_noise() -> FastNoiseLite {
+    CLOUD_NOISE.create()
 }
 
-fn create_simple_noise() -> FastNoiseLite {
-    let mut noise = FastNoiseLite::with_seed(2021);
-    noise.set_noise_type(Some(NoiseType::OpenSimplex2));
-    noise.set_fractal_type(Some(FractalType::Ridged));
-    noise.set_fractal_octaves(Some(4));
-    noise.set_fractal_lacunarity(Some(2.0));
-    noise.set_fractal_gain(Some(0.4));
-    noise.set_frequency(Some(0.2));
-    noise
+pub const CLOUD_NOISE: NoiseSettings = NoiseSettings {
+    seed: Some(805),
+    noise_type: Some(NoiseType::Perlin),
+    fractal_type: Some(FractalType::FBm),
+    octaves: Some(2),
+    lacunarity: Some(3.0),
+    gain: Some(0.5),
+    frequency: Some(0.01),
+};
+
+pub const KENSHI_NOISE: NoiseSettings = NoiseSettings {
+    seed: Some(623),
+    noise_type: Some(NoiseType::OpenSimplex2S),
+    fractal_type: Some(FractalType::Ridged),
+    octaves: Some(5),
+    lacunarity: Some(2.0),
+    gain: Some(0.5),
+    frequency: Some(0.8),
+};
+
+pub const RATCHET_T_NOISE: NoiseSettings = NoiseSettings {
+    seed: Some(3344),
+    noise_type: Some(NoiseType::Perlin),
+    fractal_type: Some(FractalType::Ridged),
+    octaves: Some(8),
+    lacunarity: Some(3.0),
+    gain: Some(0.7),
+    frequency: Some(1.8),
+};
+
+pub const MOON_NOISE: NoiseSettings = NoiseSettings {
+    seed: Some(4321),
+    noise_type: Some(NoiseType::OpenSimplex2),
+    fractal_type: Some(FractalType::PingPong),
+    octaves: Some(2),
+    lacunarity: Some(2.0),
+    gain: Some(0.5),
+    frequency: Some(3.0),
+};
+
+pub const ROCKY_NOISE: NoiseSettings = NoiseSettings {
+    seed: Some(4321),
+    noise_type: Some(NoiseType::Perlin),
+    fractal_type: Some(FractalType::PingPong),
+    octaves: Some(5),
+    lacunarity: Some(2.0),
+    gain: Some(1.0),
+    frequency: Some(5.0),
+};
+
+pub const RATCHET_NOISE: NoiseSettings = NoiseSettings {
+    seed: Some(9876),
+    noise_type: Some(NoiseType::OpenSimplex2),
+    fractal_type: Some(FractalType::DomainWarpProgressive),
+    octaves: Some(6),
+    lacunarity: Some(2.0),
+    gain: Some(0.5),
+    frequency: Some(2.0),
+};
+
+pub const SIMPLE_NOISE: NoiseSettings = NoiseSettings {
+    seed: Some(2021),
+    noise_type: Some(NoiseType::OpenSimplex2),
+    fractal_type: Some(FractalType::Ridged),
+    octaves: Some(4),
+    lacunarity: Some(2.0),
+    gain: Some(0.4),
+    frequency: Some(0.2),
+};
+
+pub fn create_moon_noise() -> FastNoiseLite {
+    MOON_NOISE.create()
 }
 
+// Bandas de los anillos: se muestrea en una dimensión, con el radio
+pub const RING_NOISE: NoiseSettings = NoiseSettings {
+    seed: Some(1610),
+    noise_type: Some(NoiseType::OpenSimplex2),
+    fractal_type: Some(FractalType::FBm),
+    octaves: Some(4),
+    lacunarity: Some(2.3),
+    gain: Some(0.55),
+    frequency: Some(6.0),
+};
+
+// Nubes de la Vía Láctea en el fondo; se muestrea con la dirección de vista
+pub const MILKY_WAY_NOISE: NoiseSettings = NoiseSettings {
+    seed: Some(1),
+    noise_type: Some(NoiseType::OpenSimplex2),
+    fractal_type: Some(FractalType::FBm),
+    octaves: Some(5),
+    lacunarity: Some(2.0),
+    gain: Some(0.5),
+    frequency: Some(0.03),
+};
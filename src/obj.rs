@@ -1,9 +1,10 @@
-use tobj;
+use std::path::Path;
 use nalgebra_glm::{Vec2, Vec3};
 use crate::vertex::Vertex;
 
 pub struct Obj {
     meshes: Vec<Mesh>,
+    materials: Vec<ObjMaterial>,
 }
 
 struct Mesh {
@@ -11,16 +12,40 @@ struct Mesh {
     normals: Vec<Vec3>,
     texcoords: Vec<Vec2>,
     indices: Vec<u32>,
+    material_id: Option<usize>,
+}
+
+// Material del archivo MTL. La ruta de la textura es relativa al directorio
+// de trabajo (ya resuelta desde la carpeta del OBJ).
+pub struct ObjMaterial {
+    pub name: String,
+    pub diffuse: Vec3,
+    pub specular: Vec3,
+    pub shininess: f32,
+    pub diffuse_texture: Option<String>,
 }
 
 impl Obj {
     pub fn load_custom_obj(filename: &str) -> Result<Self, tobj::LoadError> {
-        let (models, _) = tobj::load_obj(filename, &tobj::LoadOptions {
+        let (models, materials) = tobj::load_obj(filename, &tobj::LoadOptions {
             single_index: true,
             triangulate: true,
             ..Default::default()
         })?;
 
+        let directory = Path::new(filename).parent().unwrap_or(Path::new(""));
+        // Si el MTL no existe (sphere.obj apunta a uno que no está) la malla se
+        // dibuja con el material por defecto.
+        let materials = materials.unwrap_or_default().into_iter().map(|material| ObjMaterial {
+            name: material.name,
+            diffuse: Vec3::from(material.diffuse),
+            specular: Vec3::from(material.specular),
+            shininess: material.shininess,
+            diffuse_texture: (!material.diffuse_texture.is_empty()).then(|| {
+                directory.join(&material.diffuse_texture).to_string_lossy().into_owned()
+            }),
+        }).collect();
+
         let meshes = models.into_iter().map(|model| {
             let mesh = model.mesh;
             Mesh {
@@ -34,29 +59,58 @@ impl Obj {
                     .map(|t| Vec2::new(t[0], 1.0 - t[1]))
                     .collect(),
                 indices: mesh.indices,
+                material_id: mesh.material_id,
             }
         }).collect();
 
-        Ok(Obj { meshes })
+        Ok(Obj { meshes, materials })
+    }
+
+    pub fn materials(&self) -> &[ObjMaterial] {
+        &self.materials
     }
 
     pub fn get_vertex_array(&self) -> Vec<Vertex> {
         let mut vertices = Vec::new();
 
         for mesh in &self.meshes {
-            for &index in &mesh.indices {
-                let position = mesh.vertices[index as usize];
-                let normal = mesh.normals.get(index as usize)
-                    .cloned()
-                    .unwrap_or(Vec3::new(0.0, 1.0, 0.0));
-                let tex_coords = mesh.texcoords.get(index as usize)
-                    .cloned()
-                    .unwrap_or(Vec2::new(0.0, 0.0));
-
-                vertices.push(Vertex::new(position, normal, tex_coords));
-            }
+            mesh.push_vertices(&mut vertices);
         }
 
         vertices
     }
+
+    // Vértices agrupados por material, en el orden en que aparece cada material.
+    pub fn get_material_groups(&self) -> Vec<(Option<usize>, Vec<Vertex>)> {
+        let mut groups: Vec<(Option<usize>, Vec<Vertex>)> = Vec::new();
+
+        for mesh in &self.meshes {
+            let index = match groups.iter().position(|(material, _)| *material == mesh.material_id) {
+                Some(index) => index,
+                None => {
+                    groups.push((mesh.material_id, Vec::new()));
+                    groups.len() - 1
+                }
+            };
+            mesh.push_vertices(&mut groups[index].1);
+        }
+
+        groups
+    }
+}
+
+impl Mesh {
+    fn push_vertices(&self, vertices: &mut Vec<Vertex>) {
+        for &index in &self.indices {
+            let position = self.vertices[index as usize];
+            let normal = self.normals.get(index as usize)
+                .cloned()
+                .unwrap_or(Vec3::new(0.0, 1.0, 0.0));
+            let tex_coords = self.texcoords.get(index as usize)
+                .cloned()
+                .unwrap_or(Vec2::new(0.0, 0.0));
+
+            vertices.push(Vertex::new(position, normal, tex_coords));
+        }
+    }
 }
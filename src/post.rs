@@ -0,0 +1,363 @@
+use nalgebra_glm::smoothstep;
+use rayon::prelude::*;
+use serde::Deserialize;
+use crate::color::Color;
+use crate::framebuffer::Framebuffer;
+use crate::render::gaussian_blur;
+
+// Efectos de postproceso sobre el color HDR del framebuffer, antes del tone
+// mapping. Se aplican en el orden de la cadena, que se puede editar mientras
+// corre el programa (agregar, quitar, reordenar o apagar efectos).
+
+pub trait PostEffect {
+    // Nombre con el que se busca el efecto en la cadena (igual que en la escena)
+    fn name(&self) -> &'static str;
+
+    fn apply(&mut self, framebuffer: &mut Framebuffer, time: u32);
+}
+
+pub struct PostStage {
+    pub enabled: bool,
+    pub effect: Box<dyn PostEffect>,
+}
+
+#[derive(Default)]
+pub struct PostChain {
+    pub stages: Vec<PostStage>,
+}
+
+impl PostChain {
+    pub fn new() -> Self {
+        PostChain::default()
+    }
+
+    pub fn push(&mut self, effect: impl PostEffect + 'static) {
+        self.stages.push(PostStage { enabled: true, effect: Box::new(effect) });
+    }
+
+    // Primera etapa con el efecto `name`.
+    pub fn find_mut(&mut self, name: &str) -> Option<&mut PostStage> {
+        self.stages.iter_mut().find(|stage| stage.effect.name() == name)
+    }
+
+    // Enciende o apaga todas las etapas con el efecto `name`.
+    pub fn set_enabled(&mut self, name: &str, enabled: bool) {
+        for stage in self.stages.iter_mut().filter(|stage| stage.effect.name() == name) {
+            stage.enabled = enabled;
+        }
+    }
+
+    pub fn toggle(&mut self, name: &str) {
+        for stage in self.stages.iter_mut().filter(|stage| stage.effect.name() == name) {
+            stage.enabled = !stage.enabled;
+        }
+    }
+
+    pub fn apply(&mut self, framebuffer: &mut Framebuffer, time: u32) {
+        for stage in self.stages.iter_mut().filter(|stage| stage.enabled) {
+            stage.effect.apply(framebuffer, time);
+        }
+    }
+}
+
+// Desenfoque gaussiano de toda la imagen.
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(default)]
+pub struct BlurSettings {
+    // Desviación estándar en píxeles
+    pub sigma: f32,
+}
+
+impl Default for BlurSettings {
+    fn default() -> Self {
+        BlurSettings { sigma: 2.0 }
+    }
+}
+
+pub struct Blur {
+    pub settings: BlurSettings,
+}
+
+impl Blur {
+    pub fn new(settings: BlurSettings) -> Self {
+        Blur { settings }
+    }
+}
+
+impl PostEffect for Blur {
+    fn name(&self) -> &'static str {
+        "blur"
+    }
+
+    fn apply(&mut self, framebuffer: &mut Framebuffer, _time: u32) {
+        let (width, height) = (framebuffer.width, framebuffer.height);
+        gaussian_blur(&mut framebuffer.hdr_buffer, width, height, self.settings.sigma);
+    }
+}
+
+// Oscurece los bordes de la imagen.
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(default)]
+pub struct VignetteSettings {
+    // Cuánto se oscurecen las esquinas (0 = nada, 1 = negro)
+    pub strength: f32,
+    // Distancia al centro (1 = esquina) donde empieza a oscurecer
+    pub radius: f32,
+}
+
+impl Default for VignetteSettings {
+    fn default() -> Self {
+        VignetteSettings { strength: 0.4, radius: 0.5 }
+    }
+}
+
+pub struct Vignette {
+    pub settings: VignetteSettings,
+}
+
+impl Vignette {
+    pub fn new(settings: VignetteSettings) -> Self {
+        Vignette { settings }
+    }
+}
+
+impl PostEffect for Vignette {
+    fn name(&self) -> &'static str {
+        "vignette"
+    }
+
+    fn apply(&mut self, framebuffer: &mut Framebuffer, _time: u32) {
+        let settings = self.settings;
+        let (width, height) = (framebuffer.width as f32, framebuffer.height as f32);
+        let half_diagonal = (width * width + height * height).sqrt() * 0.5;
+        framebuffer.hdr_buffer.par_chunks_mut(framebuffer.width).enumerate().for_each(|(y, row)| {
+            for (x, pixel) in row.iter_mut().enumerate() {
+                let dx = x as f32 + 0.5 - width * 0.5;
+                let dy = y as f32 + 0.5 - height * 0.5;
+                let distance = (dx * dx + dy * dy).sqrt() / half_diagonal;
+                let shade = 1.0 - settings.strength * smoothstep(settings.radius, 1.0, distance);
+                *pixel = *pixel * shade;
+            }
+        });
+    }
+}
+
+// Grano de película: ruido por píxel que cambia en cada frame.
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(default)]
+pub struct GrainSettings {
+    // Variación relativa del brillo; el negro queda limpio
+    pub intensity: f32,
+    pub seed: u32,
+}
+
+impl Default for GrainSettings {
+    fn default() -> Self {
+        GrainSettings { intensity: 0.05, seed: 1 }
+    }
+}
+
+pub struct Grain {
+    pub settings: GrainSettings,
+}
+
+impl Grain {
+    pub fn new(settings: GrainSettings) -> Self {
+        Grain { settings }
+    }
+}
+
+impl PostEffect for Grain {
+    fn name(&self) -> &'static str {
+        "grain"
+    }
+
+    fn apply(&mut self, framebuffer: &mut Framebuffer, time: u32) {
+        let settings = self.settings;
+        let width = framebuffer.width;
+        framebuffer.hdr_buffer.par_chunks_mut(width).enumerate().for_each(|(y, row)| {
+            for (x, pixel) in row.iter_mut().enumerate() {
+                let noise = grain_noise(settings.seed, time, (y * width + x) as u32);
+                *pixel = *pixel * (1.0 + settings.intensity * noise).max(0.0);
+            }
+        });
+    }
+}
+
+// Valor pseudoaleatorio en [-1, 1) para un píxel y un frame.
+fn grain_noise(seed: u32, frame: u32, pixel: u32) -> f32 {
+    let mut h = seed.wrapping_mul(0x9E37_79B9) ^ frame.wrapping_mul(0x85EB_CA6B) ^ pixel.wrapping_mul(0xC2B2_AE35);
+    h ^= h >> 16;
+    h = h.wrapping_mul(0x7FEB_352D);
+    h ^= h >> 15;
+    h = h.wrapping_mul(0x846C_A68B);
+    h ^= h >> 16;
+    (h >> 8) as f32 / (1u32 << 23) as f32 - 1.0
+}
+
+// Separa los canales hacia los bordes, como una lente que no enfoca igual
+// todos los colores.
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(default)]
+pub struct ChromaticAberrationSettings {
+    // Desplazamiento del rojo y el azul en las esquinas, en fracción del
+    // ancho de la imagen
+    pub strength: f32,
+}
+
+impl Default for ChromaticAberrationSettings {
+    fn default() -> Self {
+        ChromaticAberrationSettings { strength: 0.004 }
+    }
+}
+
+pub struct ChromaticAberration {
+    pub settings: ChromaticAberrationSettings,
+    scratch: Vec<Color>,
+}
+
+impl ChromaticAberration {
+    pub fn new(settings: ChromaticAberrationSettings) -> Self {
+        ChromaticAberration { settings, scratch: Vec::new() }
+    }
+}
+
+impl PostEffect for ChromaticAberration {
+    fn name(&self) -> &'static str {
+        "chromatic_aberration"
+    }
+
+    fn apply(&mut self, framebuffer: &mut Framebuffer, _time: u32) {
+        if self.settings.strength == 0.0 {
+            return;
+        }
+        let (width, height) = (framebuffer.width, framebuffer.height);
+        self.scratch.clear();
+        self.scratch.extend_from_slice(&framebuffer.hdr_buffer);
+        let source = &self.scratch;
+        let sample = |x: f32, y: f32| bilinear(source, width, height, x, y);
+
+        // El desplazamiento crece con la distancia al centro
+        let scale = self.settings.strength * width as f32 / (width.max(height) as f32 * 0.5);
+        let (center_x, center_y) = (width as f32 * 0.5, height as f32 * 0.5);
+        framebuffer.hdr_buffer.par_chunks_mut(width).enumerate().for_each(|(y, row)| {
+            for (x, pixel) in row.iter_mut().enumerate() {
+                let dx = x as f32 + 0.5 - center_x;
+                let dy = y as f32 + 0.5 - center_y;
+                let red = sample(center_x + dx * (1.0 + scale) - 0.5, center_y + dy * (1.0 + scale) - 0.5);
+                let blue = sample(center_x + dx * (1.0 - scale) - 0.5, center_y + dy * (1.0 - scale) - 0.5);
+                *pixel = Color { r: red.r, b: blue.b, ..*pixel };
+            }
+        });
+    }
+}
+
+fn bilinear(pixels: &[Color], width: usize, height: usize, x: f32, y: f32) -> Color {
+    let texel = |x: i32, y: i32| {
+        let x = x.clamp(0, width as i32 - 1) as usize;
+        let y = y.clamp(0, height as i32 - 1) as usize;
+        pixels[y * width + x]
+    };
+    let (x0, y0) = (x.floor(), y.floor());
+    let (tx, ty) = (x - x0, y - y0);
+    let (x0, y0) = (x0 as i32, y0 as i32);
+    let top = texel(x0, y0).lerp(&texel(x0 + 1, y0), tx);
+    let bottom = texel(x0, y0 + 1).lerp(&texel(x0 + 1, y0 + 1), tx);
+    top.lerp(&bottom, ty)
+}
+
+// Realza los bordes restando a cada píxel el promedio de sus vecinos.
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(default)]
+pub struct SharpenSettings {
+    pub amount: f32,
+}
+
+impl Default for SharpenSettings {
+    fn default() -> Self {
+        SharpenSettings { amount: 0.5 }
+    }
+}
+
+pub struct Sharpen {
+    pub settings: SharpenSettings,
+    scratch: Vec<Color>,
+}
+
+impl Sharpen {
+    pub fn new(settings: SharpenSettings) -> Self {
+        Sharpen { settings, scratch: Vec::new() }
+    }
+}
+
+impl PostEffect for Sharpen {
+    fn name(&self) -> &'static str {
+        "sharpen"
+    }
+
+    fn apply(&mut self, framebuffer: &mut Framebuffer, _time: u32) {
+        let amount = self.settings.amount;
+        if amount == 0.0 {
+            return;
+        }
+        let (width, height) = (framebuffer.width, framebuffer.height);
+        self.scratch.clear();
+        self.scratch.extend_from_slice(&framebuffer.hdr_buffer);
+        let source = &self.scratch;
+        let texel = |x: usize, y: usize, dx: i32, dy: i32| {
+            let x = (x as i32 + dx).clamp(0, width as i32 - 1) as usize;
+            let y = (y as i32 + dy).clamp(0, height as i32 - 1) as usize;
+            source[y * width + x]
+        };
+        framebuffer.hdr_buffer.par_chunks_mut(width).enumerate().for_each(|(y, row)| {
+            for (x, pixel) in row.iter_mut().enumerate() {
+                let neighbors = texel(x, y, -1, 0) + texel(x, y, 1, 0) + texel(x, y, 0, -1) + texel(x, y, 0, 1);
+                // Por canal y sin pasar por `Color * f32`, que recorta los negativos
+                let sharpen = |center: f32, around: f32| (center * (1.0 + 4.0 * amount) - around * amount).max(0.0);
+                *pixel = Color {
+                    r: sharpen(pixel.r, neighbors.r),
+                    g: sharpen(pixel.g, neighbors.g),
+                    b: sharpen(pixel.b, neighbors.b),
+                    ..*pixel
+                };
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sharpen(framebuffer: &mut Framebuffer) {
+        Sharpen::new(SharpenSettings::default()).apply(framebuffer, 0);
+    }
+
+    #[test]
+    fn sharpen_keeps_flat_image() {
+        let mut framebuffer = Framebuffer::new(16, 12);
+        framebuffer.hdr_buffer.fill(Color::from_float(0.3, 0.6, 2.5));
+        sharpen(&mut framebuffer);
+        for pixel in &framebuffer.hdr_buffer {
+            assert!((pixel.r - 0.3).abs() < 1e-5, "{}", pixel);
+            assert!((pixel.g - 0.6).abs() < 1e-5, "{}", pixel);
+            assert!((pixel.b - 2.5).abs() < 1e-5, "{}", pixel);
+        }
+    }
+
+    #[test]
+    fn sharpen_overshoots_edges() {
+        // Mitad izquierda oscura y mitad derecha clara
+        let (width, height) = (16, 12);
+        let mut framebuffer = Framebuffer::new(width, height);
+        for (index, pixel) in framebuffer.hdr_buffer.iter_mut().enumerate() {
+            let value = if index % width < width / 2 { 0.2 } else { 0.8 };
+            *pixel = Color::from_float(value, value, value);
+        }
+        sharpen(&mut framebuffer);
+        let row = &framebuffer.hdr_buffer[(height / 2) * width..(height / 2 + 1) * width];
+        assert!(row[width / 2 - 1].r < 0.2, "dark side {}", row[width / 2 - 1]);
+        assert!(row[width / 2].r > 0.8, "bright side {}", row[width / 2]);
+        assert!((row[0].r - 0.2).abs() < 1e-5 && (row[width - 1].r - 0.8).abs() < 1e-5);
+    }
+}
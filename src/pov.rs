@@ -1,5 +1,5 @@
 use nalgebra_glm::{Vec3, rotate_vec3};
-use crate::PI;
+use std::f32::consts::PI;
 
 pub struct POV {
     pub eye: Vec3,
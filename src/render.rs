@@ -1,14 +1,27 @@
-use nalgebra_glm::{Vec3, Mat4, look_at, perspective};
+use nalgebra_glm::{Vec2, Vec3, Mat3, Mat4, look_at, perspective};
 use std::f32::consts::PI;
 use fastnoise_lite::FastNoiseLite;
 use crate::vertex::Vertex;
-use crate::shader::vertex_shader;
-use crate::Framebuffer;
-use crate::line::triangle_flat_shade;
+use crate::shader::{vertex_shader, FragmentShader};
+use crate::framebuffer::{split_tiles, Framebuffer, FramebufferTile, GSample};
+use crate::fragment::Fragment;
 use crate::color::Color;
+use crate::line::{calculate_bounding_box, triangle_flat_shade, triangle_fragments, ScreenRect};
+use rayon::prelude::*;
+use std::sync::Arc;
+use crate::texture::Texture;
+use crate::material::SurfaceMaterial;
+use crate::light::Light;
+use crate::shadow::ShadowMap;
+use crate::clipping::{clip_triangle, to_screen};
+use crate::culling::{is_face_culled, CullMode, FrontFace};
+use crate::blend::BlendState;
+use crate::atmosphere::Atmosphere;
+use crate::rings::RingPlane;
 
 pub struct Uniforms {
     pub model_matrix: Mat4,
+    pub normal_matrix: Mat3,
     pub view_matrix: Mat4,
     pub projection_matrix: Mat4,
     pub viewport_matrix: Mat4,
@@ -16,69 +29,226 @@ pub struct Uniforms {
     pub noise: FastNoiseLite,
     pub cloud_noise: FastNoiseLite, 
     pub band_noise: FastNoiseLite, 
-    pub current_shader: u8, 
+    pub cull_mode: CullMode,
+    pub front_face: FrontFace,
+    pub textures: Vec<Arc<Texture>>,
+    pub material: SurfaceMaterial,
+    pub camera_position: Vec3,
+    pub lights: Vec<Light>,
+    pub shadow_map: Option<Arc<ShadowMap>>,
+    pub atmosphere: Option<Atmosphere>,
+    pub rings: Vec<RingPlane>,
 }
 
-pub fn render(framebuffer: &mut Framebuffer, uniforms: &Uniforms, vertex_array: &[Vertex], time: u32) {
-    let mut transformed_vertices = Vec::with_capacity(vertex_array.len());
-    for vertex in vertex_array {
-        let transformed = vertex_shader(vertex, uniforms);
-        transformed_vertices.push(transformed);
+impl Uniforms {
+    pub fn new(view_matrix: Mat4, projection_matrix: Mat4, viewport_matrix: Mat4, time: u32) -> Self {
+        Uniforms {
+            model_matrix: Mat4::identity(),
+            normal_matrix: Mat3::identity(),
+            view_matrix,
+            projection_matrix,
+            viewport_matrix,
+            time,
+            noise: FastNoiseLite::new(),
+            cloud_noise: FastNoiseLite::new(),
+            band_noise: FastNoiseLite::new(),
+            cull_mode: CullMode::default(),
+            front_face: FrontFace::default(),
+            textures: Vec::new(),
+            material: SurfaceMaterial::default(),
+            camera_position: camera_position(&view_matrix),
+            lights: Vec::new(),
+            shadow_map: None,
+            atmosphere: None,
+            rings: Vec::new(),
+        }
     }
+}
+
+// Posición de la cámara en espacio de mundo, a partir de la matriz de vista.
+fn camera_position(view_matrix: &Mat4) -> Vec3 {
+    let eye = view_matrix.try_inverse().unwrap_or_else(Mat4::identity).column(3).into_owned();
+    Vec3::new(eye.x, eye.y, eye.z)
+}
+
+// Tamaño en píxeles de los tiles del rasterizador
+const TILE_SIZE: usize = 32;
+
+// Material de un cuerpo para el pase de sombreado diferido: su shader y los
+// uniforms (matrices y ruidos) con los que se dibujó.
+pub struct Material<'a> {
+    pub shader: &'a dyn FragmentShader,
+    pub uniforms: Uniforms,
+}
+
+// Render directo: sombrea cada fragmento al rasterizar.
+pub fn render(framebuffer: &mut Framebuffer, uniforms: &Uniforms, vertex_array: &[Vertex], shader: &dyn FragmentShader, time: u32) {
+    let triangles = process_vertices(uniforms, vertex_array);
 
-    let mut triangles = Vec::new();
-    for i in (0..transformed_vertices.len()).step_by(3) {
-        if i + 2 < transformed_vertices.len() {
-            triangles.push([
-                transformed_vertices[i].clone(),
-                transformed_vertices[i + 1].clone(),
-                transformed_vertices[i + 2].clone(),
-            ]);
+    rasterize_tiles(framebuffer, &triangles, |tile, rect, tri| {
+        // Pasa los parámetros adicionales `uniforms`, `shader` y `time` a la función
+        for fragment in triangle_flat_shade(&tri[0], &tri[1], &tri[2], uniforms, shader, time, rect) {
+            let x = fragment.position.x as usize;
+            let y = fragment.position.y as usize;
+            tile.point(x, y, fragment.color, fragment.depth, fragment.emission);
         }
-    }
+    });
+}
 
-    let mut fragments = Vec::new();
-    for tri in &triangles {
-        // Pasa los parámetros adicionales `uniforms` y `time` a la función
-        fragments.extend(triangle_flat_shade(&tri[0], &tri[1], &tri[2], uniforms, time));
-    }
+// Pasada transparente: sombrea cada fragmento y lo mezcla con el framebuffer.
+pub fn render_transparent(framebuffer: &mut Framebuffer, uniforms: &Uniforms, vertex_array: &[Vertex], shader: &dyn FragmentShader, blend: &BlendState, time: u32) {
+    let triangles = process_vertices(uniforms, vertex_array);
 
-    for fragment in fragments {
-        let x = fragment.position.x as usize;
-        let y = fragment.position.y as usize;
-        if x < framebuffer.width && y < framebuffer.height {
-            framebuffer.set_current_color(fragment.color.to_u32());
-            framebuffer.point(x, y, fragment.depth, (fragment.intensity * 255.0) as u32);
+    rasterize_tiles(framebuffer, &triangles, |tile, rect, tri| {
+        for fragment in triangle_flat_shade(&tri[0], &tri[1], &tri[2], uniforms, shader, time, rect) {
+            let x = fragment.position.x as usize;
+            let y = fragment.position.y as usize;
+            tile.blend(x, y, fragment.color, fragment.emission, fragment.depth, blend);
         }
-    }
+    });
 }
 
-pub fn render_sun(
-    framebuffer: &mut Framebuffer,
-    uniforms: &mut Uniforms,
-    vertex_array: &Vec<Vertex>,
-    sun_position: Vec3,
-    sun_size: f32,
-    time: usize,
-) {
-    // Set dynamic color and emission for the sun
-    let dynamic_emission = 100 + (50.0 * (time as f32 * 0.05).sin()) as u32;
-    let dynamic_color = Color::from_float(
-        1.0,
-        0.9 + 0.1 * (time as f32 * 0.1).cos(),
-        0.5 + 0.2 * (time as f32 * 0.1).sin(),
-    );
+// Pase de geometría del render diferido: solo escribe profundidad y G-buffer.
+pub fn render_geometry(framebuffer: &mut Framebuffer, uniforms: &Uniforms, vertex_array: &[Vertex], material: u32) {
+    let triangles = process_vertices(uniforms, vertex_array);
+
+    rasterize_tiles(framebuffer, &triangles, |tile, rect, tri| {
+        for fragment in triangle_fragments(&tri[0], &tri[1], &tri[2], rect) {
+            let x = fragment.position.x as usize;
+            let y = fragment.position.y as usize;
+            tile.sample(x, y, fragment.depth, GSample {
+                normal: fragment.normal,
+                vertex_position: fragment.vertex_position,
+                world_position: fragment.world_position,
+                uv: fragment.uv.unwrap_or(Vec2::new(0.0, 0.0)),
+                uv_footprint: fragment.uv_footprint,
+                material,
+            });
+        }
+    });
+}
 
-    framebuffer.set_emission(dynamic_emission);
-    uniforms.current_shader = 1;
+// Pase de profundidad (mapas de sombra): solo escribe en `depth`, un buffer de
+// `width` x `height` sin color ni G-buffer.
+pub fn render_depth(depth: &mut [f32], width: usize, height: usize, uniforms: &Uniforms, vertex_array: &[Vertex]) {
+    let triangles = process_vertices(uniforms, vertex_array);
+    let bins = bin_triangles(&triangles, width, height);
+    let tiles_x = width.div_ceil(TILE_SIZE);
+
+    split_tiles(depth, width, TILE_SIZE).into_par_iter().zip(bins.par_iter()).enumerate().for_each(|(tile, (mut rows, bin))| {
+        let (x_start, y_start) = (tile % tiles_x * TILE_SIZE, tile / tiles_x * TILE_SIZE);
+        let rect = tile_rect(x_start, y_start, rows[0].len(), rows.len());
+        for &index in bin {
+            let tri = &triangles[index];
+            for fragment in triangle_fragments(&tri[0], &tri[1], &tri[2], &rect) {
+                let row = &mut rows[fragment.position.y as usize - y_start];
+                let depth = &mut row[fragment.position.x as usize - x_start];
+                if *depth > fragment.depth {
+                    *depth = fragment.depth;
+                }
+            }
+        }
+    });
+}
 
-    uniforms.model_matrix = create_model_matrix(sun_position, sun_size, Vec3::new(0.0, 0.0, 0.0));
-    render(framebuffer, uniforms, vertex_array, time as u32);
+// Pase de sombreado: ejecuta el shader una sola vez por píxel visible.
+pub fn shade_deferred(framebuffer: &mut Framebuffer, materials: &[Material], time: u32) {
+    framebuffer.bands_mut(TILE_SIZE).for_each(|band| {
+        for (index, sample) in band.gbuffer.iter().enumerate() {
+            let Some(material) = materials.get(sample.material as usize) else {
+                continue;
+            };
+            let x = index % band.width;
+            let y = band.y_start + index / band.width;
+            let mut fragment = Fragment::new(
+                Vec2::new(x as f32 + 0.5, y as f32 + 0.5),
+                Color::black(),
+                band.zbuffer[index],
+                sample.normal,
+                sample.vertex_position,
+                sample.world_position,
+                Some(sample.uv),
+            );
+            fragment.uv_footprint = sample.uv_footprint;
+            let (color, emission) = material.shader.shade(&fragment, &material.uniforms, time);
+            band.buffer[index] = color + emission;
+            band.emissive_buffer[index] = emission;
+        }
+    });
 }
 
-fn apply_sun_effects(framebuffer: &mut Framebuffer, width: usize, height: usize) {
-    gaussian_blur(&mut framebuffer.emissive_buffer, width, height, 50, 2.0);
-    apply_bloom(&mut framebuffer.buffer, &framebuffer.emissive_buffer, width, height);
+// Vertex shader, recorte contra el frustum, división de perspectiva y descarte de caras
+fn process_vertices(uniforms: &Uniforms, vertex_array: &[Vertex]) -> Vec<[Vertex; 3]> {
+    let transformed_vertices: Vec<Vertex> = vertex_array
+        .par_iter()
+        .map(|vertex| vertex_shader(vertex, uniforms))
+        .collect();
+
+    transformed_vertices
+        .par_chunks_exact(3)
+        .flat_map_iter(|vertices| {
+            let triangle = [vertices[0].clone(), vertices[1].clone(), vertices[2].clone()];
+            clip_triangle(&triangle).into_iter().filter_map(|mut clipped| {
+                for vertex in clipped.iter_mut() {
+                    to_screen(vertex, &uniforms.viewport_matrix);
+                }
+                (!is_face_culled(&clipped, uniforms.cull_mode, uniforms.front_face)).then_some(clipped)
+            })
+        })
+        .collect()
+}
+
+// Reparte los triángulos en tiles de TILE_SIZE x TILE_SIZE y rasteriza los
+// tiles en paralelo, cada uno escribiendo directamente en su región del
+// framebuffer. Dentro de un tile se respeta el orden de los triángulos.
+fn rasterize_tiles<F>(framebuffer: &mut Framebuffer, triangles: &[[Vertex; 3]], raster: F)
+where
+    F: Fn(&mut FramebufferTile, &ScreenRect, &[Vertex; 3]) + Sync,
+{
+    let bins = bin_triangles(triangles, framebuffer.width, framebuffer.height);
+
+    framebuffer.tiles_mut(TILE_SIZE).into_par_iter().zip(bins.par_iter()).for_each(|(mut tile, bin)| {
+        let rect = tile_rect(tile.x_start, tile.y_start, tile.width(), tile.height());
+        for &index in bin {
+            raster(&mut tile, &rect, &triangles[index]);
+        }
+    });
+}
+
+fn tile_rect(x_start: usize, y_start: usize, width: usize, height: usize) -> ScreenRect {
+    ScreenRect {
+        min_x: x_start as i32,
+        min_y: y_start as i32,
+        max_x: (x_start + width) as i32 - 1,
+        max_y: (y_start + height) as i32 - 1,
+    }
+}
+
+// Índices de los triángulos que tocan cada tile, en su orden original. Los
+// tiles van en orden de filas, igual que `Framebuffer::tiles_mut`.
+fn bin_triangles(triangles: &[[Vertex; 3]], width: usize, height: usize) -> Vec<Vec<usize>> {
+    let tiles_x = width.div_ceil(TILE_SIZE);
+    let mut bins: Vec<Vec<usize>> = vec![Vec::new(); tiles_x * height.div_ceil(TILE_SIZE)];
+    for (index, tri) in triangles.iter().enumerate() {
+        let (min_x, min_y, max_x, max_y) = calculate_bounding_box(
+            &tri[0].transformed_position,
+            &tri[1].transformed_position,
+            &tri[2].transformed_position,
+        );
+        if max_x < 0 || max_y < 0 || min_x >= width as i32 || min_y >= height as i32 {
+            continue;
+        }
+        let first_x = min_x.max(0) as usize / TILE_SIZE;
+        let first_y = min_y.max(0) as usize / TILE_SIZE;
+        let last_x = (max_x as usize).min(width - 1) / TILE_SIZE;
+        let last_y = (max_y as usize).min(height - 1) / TILE_SIZE;
+        for tile_y in first_y..=last_y {
+            for bin in &mut bins[tile_y * tiles_x + first_x..=tile_y * tiles_x + last_x] {
+                bin.push(index);
+            }
+        }
+    }
+    bins
 }
 
 // Matrices transformations
@@ -121,6 +291,12 @@ pub fn create_model_matrix(translation: Vec3, scale: f32, rotation: Vec3) -> Mat
     transform_matrix * rotation_matrix
 }
 
+// Inversa transpuesta de la parte 3x3 del modelo, para transformar normales
+pub fn create_normal_matrix(model_matrix: &Mat4) -> Mat3 {
+    let linear = model_matrix.fixed_slice::<3, 3>(0, 0).into_owned();
+    linear.try_inverse().unwrap_or_else(Mat3::identity).transpose()
+}
+
 pub fn create_view_matrix(eye: Vec3, center: Vec3, up: Vec3) -> Mat4 {
     look_at(&eye, &center, &up)
 }
@@ -143,86 +319,106 @@ pub fn create_viewport_matrix(width: f32, height: f32) -> Mat4 {
     )
 }
 
-// Blur and Bloom effects
-
-pub fn gaussian_blur(buffer: &mut [u32], width: usize, height: usize, kernel_size: usize, sigma: f32) {
-    let gaussian_kernel = create_gaussian_kernel(kernel_size, sigma);
-    let kernel_sum: f32 = gaussian_kernel.iter().map(|&x| x as f32).sum();
+// Blur
 
-    // Apply horizontally
-    for y in 0..height {
-        let mut temp_row = vec![0u32; width];
-        for x in 0..width {
-            let mut filtered_pixel = 0f32;
-            for k in 0..gaussian_kernel.len() {
-                let sample_x = x as i32 + k as i32 - (gaussian_kernel.len() / 2) as i32;
-                if sample_x >= 0 && sample_x < width as i32 {
-                    filtered_pixel += buffer[sample_x as usize + y * width] as f32 * gaussian_kernel[k] as f32;
-                }
-            }
-            temp_row[x] = (filtered_pixel / kernel_sum).round() as u32;
-        }
-        buffer[y * width..(y + 1) * width].copy_from_slice(&temp_row);
+// Desenfoque gaussiano separable, por canal y en punto flotante. Para radios
+// grandes conviene reducir la imagen antes, como hace `bloom::Bloom`.
+pub fn gaussian_blur(buffer: &mut [Color], width: usize, height: usize, sigma: f32) {
+    if sigma <= 0.0 || width == 0 || height == 0 {
+        return;
     }
+    let kernel = create_gaussian_kernel(sigma);
+    // Filas, transposición, filas (que eran columnas) y transposición de vuelta
+    let mut temp = vec![Color::black(); buffer.len()];
+    blur_rows(buffer, &mut temp, width, &kernel);
+    transpose(&temp, buffer, width, height);
+    blur_rows(buffer, &mut temp, height, &kernel);
+    transpose(&temp, buffer, height, width);
+}
 
-    // Apply vertically
-    for x in 0..width {
-        let mut temp_col = vec![0u32; height];
-        for y in 0..height {
-            let mut filtered_pixel = 0f32;
-            for k in 0..gaussian_kernel.len() {
-                let sample_y = y as i32 + k as i32 - (gaussian_kernel.len() / 2) as i32;
-                if sample_y >= 0 && sample_y < height as i32 {
-                    filtered_pixel += buffer[x + sample_y as usize * width] as f32 * gaussian_kernel[k] as f32;
-                }
+// Pesos normalizados (suman 1) de una gaussiana, de -3 sigma a +3 sigma.
+pub fn create_gaussian_kernel(sigma: f32) -> Vec<f32> {
+    let radius = (sigma * 3.0).ceil() as i32;
+    let weights: Vec<f32> = (-radius..=radius)
+        .map(|offset| (-(offset * offset) as f32 / (2.0 * sigma * sigma)).exp())
+        .collect();
+    let sum: f32 = weights.iter().sum();
+    weights.into_iter().map(|weight| weight / sum).collect()
+}
+
+// Los bordes repiten el último píxel.
+fn blur_rows(source: &[Color], target: &mut [Color], width: usize, kernel: &[f32]) {
+    let last = width as i32 - 1;
+    let radius = (kernel.len() / 2) as i32;
+    target.par_chunks_mut(width).zip(source.par_chunks(width)).for_each(|(row, source_row)| {
+        for (x, pixel) in row.iter_mut().enumerate() {
+            let mut sum = Vec3::zeros();
+            for (k, weight) in kernel.iter().enumerate() {
+                let sample = source_row[(x as i32 + k as i32 - radius).clamp(0, last) as usize];
+                sum += Vec3::new(sample.r, sample.g, sample.b) * *weight;
             }
-            temp_col[y] = (filtered_pixel / kernel_sum).round() as u32;
+            *pixel = Color::from_float(sum.x, sum.y, sum.z).with_alpha(source_row[x].a);
         }
-        for y in 0..height {
-            buffer[x + y * width] = temp_col[y];
+    });
+}
+
+// `source` tiene `height` filas de `width`; `target` queda con `width` filas de `height`.
+fn transpose(source: &[Color], target: &mut [Color], width: usize, height: usize) {
+    target.par_chunks_mut(height).enumerate().for_each(|(x, row)| {
+        for (y, pixel) in row.iter_mut().enumerate() {
+            *pixel = source[y * width + x];
         }
-    }
+    });
 }
 
-pub fn create_gaussian_kernel(size: usize, sigma: f32) -> Vec<u32> {
-    let mut kernel = vec![0u32; size];
-    let mean = (size as f32 - 1.0) / 2.0;
-    let coefficient = 1.0 / (2.0 * std::f32::consts::PI * sigma * sigma).sqrt();
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    for x in 0..size {
-        let exp_numerator = -((x as f32 - mean) * (x as f32 - mean)) / (2.0 * sigma * sigma);
-        let exp_value = (-exp_numerator).exp();
-        kernel[x] = (coefficient * exp_value * 255.0) as u32;
-    }
+    const SIGMAS: [f32; 3] = [0.8, 2.5, 6.0];
 
-    kernel
-}
+    fn image(width: usize, height: usize, pixel: impl Fn(usize, usize) -> Color) -> Vec<Color> {
+        (0..width * height).map(|index| pixel(index % width, index / width)).collect()
+    }
 
-pub fn apply_bloom(original: &mut [u32], bloom: &[u32], width: usize, height: usize) {
-    for i in 0..original.len() {
-        let original_color = original[i];
-        let bloom_intensity = bloom[i];
-        if bloom_intensity > 0 {
-            original[i] = blend_bloom(original_color, bloom_intensity);
+    #[test]
+    fn kernel_weights_sum_to_one() {
+        for sigma in SIGMAS {
+            let sum: f32 = create_gaussian_kernel(sigma).iter().sum();
+            assert!((sum - 1.0).abs() < 1e-5, "sigma {}: sum {}", sigma, sum);
         }
     }
-}
-
-pub fn blend_bloom(base_color: u32, bloom_intensity: u32) -> u32 {
-    // Bloom blending
-    let bloom_strength = 0.8;
-    let max_bloom_effect = 1.2;
-
-    let r = ((base_color >> 16) & 0xFF) as f32;
-    let g = ((base_color >> 8) & 0xFF) as f32;
-    let b = (base_color & 0xFF) as f32;
-    let bloom = bloom_intensity as f32 * bloom_strength;
 
-    // Calculate the new color intensity with clamping
-    let new_r = ((r + bloom).min(255.0 * max_bloom_effect)).min(255.0) as u32;
-    let new_g = ((g + bloom).min(255.0 * max_bloom_effect)).min(255.0) as u32;
-    let new_b = ((b + bloom).min(255.0 * max_bloom_effect)).min(255.0) as u32;
+    #[test]
+    fn constant_image_is_unchanged() {
+        let color = Color::from_float(0.3, 0.6, 2.5);
+        for sigma in SIGMAS {
+            let mut buffer = image(37, 23, |_, _| color);
+            gaussian_blur(&mut buffer, 37, 23, sigma);
+            for pixel in &buffer {
+                assert!((pixel.r - color.r).abs() < 1e-4, "sigma {}: {}", sigma, pixel);
+                assert!((pixel.g - color.g).abs() < 1e-4, "sigma {}: {}", sigma, pixel);
+                assert!((pixel.b - color.b).abs() < 1e-4, "sigma {}: {}", sigma, pixel);
+            }
+        }
+    }
 
-    (new_r << 16) | (new_g << 8) | new_b
+    #[test]
+    fn channels_do_not_bleed() {
+        // Lo bastante grande para que el kernel no llegue a los bordes
+        let (width, height) = (61, 51);
+        for sigma in SIGMAS {
+            let mut buffer = image(width, height, |x, y| {
+                if (x, y) == (width / 2, height / 2) {
+                    Color::from_float(1.0, 0.0, 0.0)
+                } else {
+                    Color::black()
+                }
+            });
+            gaussian_blur(&mut buffer, width, height, sigma);
+            assert!(buffer.iter().all(|pixel| pixel.g == 0.0 && pixel.b == 0.0), "sigma {}", sigma);
+            let red: f32 = buffer.iter().map(|pixel| pixel.r).sum();
+            assert!((red - 1.0).abs() < 1e-3, "sigma {}: red {}", sigma, red);
+        }
+    }
 }
-
@@ -0,0 +1,176 @@
+use std::f32::consts::PI;
+use std::sync::Arc;
+use fastnoise_lite::FastNoiseLite;
+use nalgebra_glm::{smoothstep, Mat4, Vec2, Vec3, Vec4};
+use serde::Deserialize;
+use crate::color::Color;
+use crate::fragment::Fragment;
+use crate::material::shade_surface;
+use crate::render::Uniforms;
+use crate::shader::FragmentShader;
+use crate::vertex::Vertex;
+
+// Anillos planetarios: un anillo plano generado en el plano XZ del cuerpo, con
+// bandas radiales de ruido y huecos. Se dibujan en la pasada transparente, la
+// sombra del planeta les llega por el mapa de sombras y su propia sombra sobre
+// el planeta se calcula cortando el rayo hacia la luz con el plano del anillo.
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct RingSettings {
+    // Radios en radios del planeta
+    pub inner: f32,
+    pub outer: f32,
+    pub segments: usize,
+    // Color en sRGB
+    pub color: [f32; 3],
+    pub opacity: f32,
+    pub gaps: Vec<RingGap>,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+pub struct RingGap {
+    pub radius: f32,
+    pub width: f32,
+}
+
+impl Default for RingSettings {
+    fn default() -> Self {
+        RingSettings {
+            inner: 1.4,
+            outer: 2.3,
+            segments: 128,
+            color: [0.85, 0.78, 0.65],
+            opacity: 0.9,
+            gaps: Vec::new(),
+        }
+    }
+}
+
+// Ancho (en unidades de la malla) del desvanecido en los bordes y en los huecos
+const EDGE_FADE: f32 = 0.03;
+
+pub struct Rings {
+    pub settings: RingSettings,
+    pub noise: FastNoiseLite,
+    // Radio del planeta en unidades de su malla
+    pub planet_radius: f32,
+}
+
+impl Rings {
+    pub fn new(settings: RingSettings, noise: FastNoiseLite, planet_radius: f32) -> Self {
+        Rings { settings, noise, planet_radius }
+    }
+
+    // Anillo de `segments` cuadriláteros con normal +Y, en unidades de la malla
+    // del planeta; u va del borde interior al exterior y v da la vuelta.
+    pub fn mesh(&self) -> Vec<Vertex> {
+        let segments = self.settings.segments.max(3);
+        let inner = self.settings.inner * self.planet_radius;
+        let outer = self.settings.outer * self.planet_radius;
+        let normal = Vec3::new(0.0, 1.0, 0.0);
+        let point = |radius: f32, step: usize| {
+            let angle = step as f32 / segments as f32 * 2.0 * PI;
+            Vec3::new(radius * angle.cos(), 0.0, radius * angle.sin())
+        };
+
+        let mut vertices = Vec::with_capacity(segments * 6);
+        for step in 0..segments {
+            let v0 = step as f32 / segments as f32;
+            let v1 = (step + 1) as f32 / segments as f32;
+            let inner0 = Vertex::new(point(inner, step), normal, Vec2::new(0.0, v0));
+            let outer0 = Vertex::new(point(outer, step), normal, Vec2::new(1.0, v0));
+            let inner1 = Vertex::new(point(inner, step + 1), normal, Vec2::new(0.0, v1));
+            let outer1 = Vertex::new(point(outer, step + 1), normal, Vec2::new(1.0, v1));
+            vertices.extend([inner0.clone(), outer0, outer1.clone(), inner0, outer1, inner1]);
+        }
+        vertices
+    }
+
+    // Opacidad del anillo a `radius` radios del planeta de su centro.
+    pub fn density(&self, radius: f32) -> f32 {
+        let settings = &self.settings;
+        if radius < settings.inner || radius > settings.outer {
+            return 0.0;
+        }
+        let edges = smoothstep(settings.inner, settings.inner + EDGE_FADE, radius)
+            * (1.0 - smoothstep(settings.outer - EDGE_FADE, settings.outer, radius));
+        let gaps = settings.gaps.iter().fold(1.0, |density, gap| {
+            let half_width = gap.width * 0.5;
+            density * smoothstep(half_width, half_width + EDGE_FADE, (radius - gap.radius).abs())
+        });
+        let bands = smoothstep(-0.6, 0.6, self.noise.get_noise_2d(radius, 0.0));
+
+        (bands * edges * gaps * settings.opacity).clamp(0.0, 1.0)
+    }
+}
+
+// Anillos colocados en el mundo para el frame actual.
+#[derive(Clone)]
+pub struct RingPlane {
+    pub rings: Arc<Rings>,
+    pub center: Vec3,
+    pub normal: Vec3,
+    // Radio del planeta en el mundo
+    pub scale: f32,
+}
+
+impl RingPlane {
+    pub fn new(rings: Arc<Rings>, model_matrix: &Mat4) -> Self {
+        let center = model_matrix * Vec4::new(0.0, 0.0, 0.0, 1.0);
+        let axis = model_matrix * Vec4::new(0.0, 1.0, 0.0, 0.0);
+        let axis = Vec3::new(axis.x, axis.y, axis.z);
+        let scale = axis.magnitude() * rings.planet_radius;
+        RingPlane {
+            rings,
+            center: Vec3::new(center.x, center.y, center.z),
+            normal: axis.normalize(),
+            scale,
+        }
+    }
+
+    // Fracción de luz que atraviesa el anillo entre `origin` y una luz a
+    // `max_distance` en la dirección `direction`.
+    pub fn transmittance(&self, origin: &Vec3, direction: &Vec3, max_distance: f32) -> f32 {
+        let denominator = direction.dot(&self.normal);
+        if denominator.abs() < 1e-6 {
+            return 1.0;
+        }
+        let distance = (self.center - origin).dot(&self.normal) / denominator;
+        // El propio anillo no se hace sombra
+        if distance <= 1e-3 * self.scale || distance >= max_distance {
+            return 1.0;
+        }
+        let hit = origin + direction * distance;
+        1.0 - self.rings.density((hit - self.center).magnitude() / self.scale)
+    }
+}
+
+// Shader de los anillos: color iluminado con la opacidad de `density`.
+pub struct RingShader {
+    pub rings: Arc<Rings>,
+}
+
+impl FragmentShader for RingShader {
+    fn shade(&self, fragment: &Fragment, uniforms: &Uniforms, _time: u32) -> (Color, Color) {
+        let position = fragment.vertex_position;
+        let radius = Vec2::new(position.x, position.z).magnitude() / self.rings.planet_radius;
+        let alpha = self.rings.density(radius);
+        if alpha <= 0.0 {
+            return (Color::black().with_alpha(0.0), Color::black());
+        }
+
+        // El anillo es plano y se ve por las dos caras: la normal mira a la cámara
+        let normal = if fragment.normal.dot(&(uniforms.camera_position - fragment.world_position)) < 0.0 {
+            -fragment.normal
+        } else {
+            fragment.normal
+        };
+        let facing = Fragment { normal, ..*fragment };
+
+        let [r, g, b] = self.rings.settings.color;
+        let tint = 1.0 + 0.15 * self.rings.noise.get_noise_2d(radius, 10.0);
+        let albedo = Color::from_srgb(r, g, b) * tint;
+        (shade_surface(uniforms, &facing, albedo, 0.0).with_alpha(alpha), Color::black())
+    }
+}
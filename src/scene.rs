@@ -0,0 +1,805 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::sync::Arc;
+use nalgebra_glm::{Mat4, Vec3};
+use serde::Deserialize;
+use crate::background::{Background, Skybox, Starfield, StarfieldSettings};
+use crate::atmosphere::{Atmosphere, AtmosphereSettings, AtmosphereShader};
+use crate::blend::{BlendMode, BlendState};
+use crate::bloom::{Bloom, BloomSettings};
+use crate::culling::{BoundingSphere, CullMode, FrontFace};
+use crate::framebuffer::Framebuffer;
+use crate::noise::{parse_fractal_type, parse_noise_type, NoiseSettings, RING_NOISE};
+use crate::light::{Emitter, Light};
+use crate::material::{LightingModel, MaterialSettings, SurfaceMaterial};
+use crate::obj::Obj;
+use crate::post::{Blur, BlurSettings, ChromaticAberration, ChromaticAberrationSettings, Grain, GrainSettings, PostChain, PostEffect, PostStage, Sharpen, SharpenSettings, Vignette, VignetteSettings};
+use crate::rings::{RingPlane, RingSettings, RingShader, Rings};
+use crate::render::{create_model_matrix, create_normal_matrix, render_depth, render_geometry, render_transparent, shade_deferred, Material, Uniforms};
+use crate::shadow::{ShadowMap, ShadowSettings};
+use crate::scene_graph::SceneGraph;
+use crate::shader::{FragmentShader, ShaderRegistry};
+use crate::texture::{Filter, Texture, WrapMode};
+use crate::tonemap::ToneMapping;
+use crate::vertex::Vertex;
+
+// Formato del archivo de escena (TOML)
+
+#[derive(Deserialize)]
+struct SceneDesc {
+    camera: CameraDesc,
+    background: Option<BackgroundDesc>,
+    #[serde(default)]
+    lights: Vec<LightDesc>,
+    #[serde(default)]
+    shadows: ShadowSettings,
+    #[serde(default)]
+    tone_mapping: ToneMapping,
+    // Atajo para una cadena con solo bloom; se ignora si hay `post`
+    bloom: Option<PostDesc<BloomSettings>>,
+    post: Option<Vec<PostDesc<EffectDesc>>>,
+    #[serde(default)]
+    systems: Vec<SystemDesc>,
+}
+
+#[derive(Deserialize)]
+struct CameraDesc {
+    eye: [f32; 3],
+    #[serde(default)]
+    center: [f32; 3],
+    #[serde(default = "default_up")]
+    up: [f32; 3],
+    #[serde(default)]
+    background: u32,
+}
+
+#[derive(Deserialize)]
+struct SystemDesc {
+    name: String,
+    bodies: Vec<BodyDesc>,
+}
+
+#[derive(Deserialize)]
+struct BodyDesc {
+    name: String,
+    #[serde(default = "default_mesh")]
+    mesh: String,
+    shader: String,
+    #[serde(default = "default_scale")]
+    scale: f32,
+    #[serde(default)]
+    position: [f32; 3],
+    #[serde(default)]
+    rotation: [f32; 3],
+    parent: Option<String>,
+    #[serde(default)]
+    cull: CullMode,
+    #[serde(default)]
+    front_face: FrontFace,
+    orbit: Option<Orbit>,
+    noise: Option<NoiseDesc>,
+    cloud_noise: Option<NoiseDesc>,
+    band_noise: Option<NoiseDesc>,
+    ring_noise: Option<NoiseDesc>,
+    #[serde(default)]
+    textures: Vec<TextureDesc>,
+    light: Option<EmitterDesc>,
+    #[serde(default)]
+    material: MaterialSettings,
+    // Con `blend` el cuerpo se dibuja en la pasada transparente
+    blend: Option<BlendMode>,
+    depth_write: Option<bool>,
+    atmosphere: Option<AtmosphereSettings>,
+    rings: Option<RingSettings>,
+}
+
+// Fondo: estrellas procedurales o un cube map de seis imágenes (+X, -X, +Y,
+// -Y, +Z, -Z). Sin fondo se usa `camera.background`.
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum BackgroundDesc {
+    Color,
+    Starfield(StarfieldSettings),
+    Skybox {
+        faces: [String; 6],
+    },
+}
+
+// Una etapa del postproceso; `enabled = false` la deja en la cadena apagada.
+#[derive(Deserialize)]
+struct PostDesc<T> {
+    #[serde(default = "default_enabled")]
+    enabled: bool,
+    #[serde(flatten)]
+    effect: T,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum EffectDesc {
+    Blur(BlurSettings),
+    Bloom(BloomSettings),
+    Vignette(VignetteSettings),
+    Grain(GrainSettings),
+    ChromaticAberration(ChromaticAberrationSettings),
+    Sharpen(SharpenSettings),
+}
+
+impl PostDesc<EffectDesc> {
+    fn into_stage(self) -> PostStage {
+        let effect: Box<dyn PostEffect> = match self.effect {
+            EffectDesc::Blur(settings) => Box::new(Blur::new(settings)),
+            EffectDesc::Bloom(settings) => Box::new(Bloom::new(settings)),
+            EffectDesc::Vignette(settings) => Box::new(Vignette::new(settings)),
+            EffectDesc::Grain(settings) => Box::new(Grain::new(settings)),
+            EffectDesc::ChromaticAberration(settings) => Box::new(ChromaticAberration::new(settings)),
+            EffectDesc::Sharpen(settings) => Box::new(Sharpen::new(settings)),
+        };
+        PostStage { enabled: self.enabled, effect }
+    }
+}
+
+// Luces de la escena, compartidas por todos los sistemas.
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum LightDesc {
+    Ambient {
+        #[serde(default = "default_light_color")]
+        color: [f32; 3],
+        #[serde(default = "default_intensity")]
+        intensity: f32,
+    },
+    Directional {
+        direction: [f32; 3],
+        #[serde(default = "default_light_color")]
+        color: [f32; 3],
+        #[serde(default = "default_intensity")]
+        intensity: f32,
+    },
+    Point {
+        position: [f32; 3],
+        #[serde(default = "default_light_color")]
+        color: [f32; 3],
+        #[serde(default = "default_intensity")]
+        intensity: f32,
+        #[serde(default)]
+        falloff: f32,
+    },
+}
+
+// Un cuerpo con `light` ilumina su sistema como luz puntual.
+#[derive(Deserialize)]
+struct EmitterDesc {
+    #[serde(default = "default_light_color")]
+    color: [f32; 3],
+    #[serde(default = "default_intensity")]
+    intensity: f32,
+    #[serde(default)]
+    falloff: f32,
+}
+
+// Una textura es una ruta de imagen o una tabla con `path` y su muestreo.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum TextureDesc {
+    Path(String),
+    Sampled {
+        path: String,
+        #[serde(default)]
+        filter: Filter,
+        #[serde(default)]
+        wrap_u: WrapMode,
+        #[serde(default)]
+        wrap_v: WrapMode,
+    },
+}
+
+// Un ruido se describe con el nombre de un preset o con sus parámetros,
+// opcionalmente partiendo de un preset.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum NoiseDesc {
+    Preset(String),
+    Custom(NoiseParams),
+}
+
+#[derive(Deserialize)]
+struct NoiseParams {
+    preset: Option<String>,
+    seed: Option<i32>,
+    noise_type: Option<String>,
+    fractal_type: Option<String>,
+    octaves: Option<i32>,
+    lacunarity: Option<f32>,
+    gain: Option<f32>,
+    frequency: Option<f32>,
+}
+
+fn default_up() -> [f32; 3] {
+    [0.0, 1.0, 0.0]
+}
+
+fn default_mesh() -> String {
+    "src/3D/sphere.obj".to_string()
+}
+
+fn default_scale() -> f32 {
+    1.0
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+fn default_light_color() -> [f32; 3] {
+    [1.0, 1.0, 1.0]
+}
+
+fn default_intensity() -> f32 {
+    1.0
+}
+
+// Escena cargada
+
+#[derive(Debug)]
+pub enum SceneError {
+    Io(io::Error),
+    Parse(toml::de::Error),
+    Mesh(String, tobj::LoadError),
+    Texture(String, image::ImageError),
+    UnknownShader(String),
+    UnknownNoise(String),
+    UnknownParent(String),
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SceneError::Io(error) => write!(f, "could not read scene: {}", error),
+            SceneError::Parse(error) => write!(f, "invalid scene: {}", error),
+            SceneError::Mesh(path, error) => write!(f, "could not load mesh {}: {}", path, error),
+            SceneError::Texture(path, error) => write!(f, "could not load texture {}: {}", path, error),
+            SceneError::UnknownShader(name) => write!(f, "unknown shader: {}", name),
+            SceneError::UnknownNoise(name) => write!(f, "unknown noise setting: {}", name),
+            SceneError::UnknownParent(name) => write!(f, "unknown parent body (parents must come first): {}", name),
+        }
+    }
+}
+
+impl Error for SceneError {}
+
+pub struct Camera {
+    pub eye: Vec3,
+    pub center: Vec3,
+    pub up: Vec3,
+    pub background: u32,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+pub struct Orbit {
+    pub radius: f32,
+    pub speed: f32,
+    #[serde(default)]
+    pub phase: f32,
+}
+
+pub struct Body {
+    pub name: String,
+    pub mesh: usize,
+    pub shader: Arc<dyn FragmentShader>,
+    pub scale: f32,
+    pub position: Vec3,
+    pub rotation: Vec3,
+    pub orbit: Option<Orbit>,
+    pub cull_mode: CullMode,
+    pub front_face: FrontFace,
+    pub noise: NoiseSettings,
+    pub cloud_noise: NoiseSettings,
+    pub band_noise: NoiseSettings,
+    pub textures: Vec<Arc<Texture>>,
+    pub light: Option<Emitter>,
+    pub material: MaterialSettings,
+    pub blend: Option<BlendState>,
+    pub atmosphere: Option<AtmosphereSettings>,
+    pub rings: Option<BodyRings>,
+}
+
+// Anillos de un cuerpo: su shader y la malla generada para ellos.
+pub struct BodyRings {
+    pub shader: RingShader,
+    pub mesh: usize,
+}
+
+pub struct System {
+    pub name: String,
+    pub graph: SceneGraph,
+}
+
+// Una parte de la malla por cada material del MTL.
+pub struct MeshPart {
+    pub vertices: Vec<Vertex>,
+    pub material: SurfaceMaterial,
+}
+
+pub struct SceneMesh {
+    pub parts: Vec<MeshPart>,
+    pub bounds: BoundingSphere,
+}
+
+pub struct Scene {
+    pub camera: Camera,
+    pub background: Background,
+    pub lights: Vec<Light>,
+    pub shadows: ShadowSettings,
+    pub tone_mapping: ToneMapping,
+    pub post: PostChain,
+    pub systems: Vec<System>,
+    pub meshes: Vec<SceneMesh>,
+    // Esfera con la que se dibujan las atmósferas
+    shell_mesh: Option<usize>,
+    shadow_map: Option<Arc<ShadowMap>>,
+}
+
+impl Scene {
+    pub fn load(path: &str, registry: &ShaderRegistry) -> Result<Self, SceneError> {
+        let source = fs::read_to_string(path).map_err(SceneError::Io)?;
+        let desc: SceneDesc = toml::from_str(&source).map_err(SceneError::Parse)?;
+
+        let mut meshes = Vec::new();
+        let mut mesh_indices: HashMap<String, usize> = HashMap::new();
+        let mut textures: HashMap<TextureKey, Arc<Texture>> = HashMap::new();
+        let mut systems = Vec::with_capacity(desc.systems.len());
+        let mut shell_mesh = None;
+
+        for system in desc.systems {
+            let mut graph = SceneGraph::new();
+            for body in system.bodies {
+                let mesh = load_mesh(&body.mesh, &mut meshes, &mut mesh_indices, &mut textures)?;
+                if body.atmosphere.is_some() && shell_mesh.is_none() {
+                    shell_mesh = Some(load_mesh(&default_mesh(), &mut meshes, &mut mesh_indices, &mut textures)?);
+                }
+
+                let shader = registry.get(&body.shader)
+                    .ok_or_else(|| SceneError::UnknownShader(body.shader.clone()))?;
+                let declared = shader.noise();
+                // El padre debe aparecer antes en el mismo sistema.
+                let parent = match &body.parent {
+                    Some(name) => Some(graph.find(name).ok_or_else(|| SceneError::UnknownParent(name.clone()))?),
+                    None => None,
+                };
+
+                let mut body_textures = Vec::with_capacity(body.textures.len());
+                for texture in &body.textures {
+                    let key = match texture {
+                        TextureDesc::Path(path) => (path.clone(), Filter::default(), WrapMode::default(), WrapMode::default()),
+                        TextureDesc::Sampled { path, filter, wrap_u, wrap_v } => (path.clone(), *filter, *wrap_u, *wrap_v),
+                    };
+                    body_textures.push(load_texture(&mut textures, key)?);
+                }
+
+                let rings = match body.rings {
+                    Some(settings) => {
+                        let noise = resolve_noise(body.ring_noise.as_ref(), RING_NOISE)?.create();
+                        let rings = Arc::new(Rings::new(settings, noise, meshes[mesh].bounds.radius));
+                        let vertices = rings.mesh();
+                        meshes.push(SceneMesh {
+                            bounds: BoundingSphere::from_vertices(&vertices),
+                            parts: vec![MeshPart { vertices, material: SurfaceMaterial::default() }],
+                        });
+                        Some(BodyRings { shader: RingShader { rings }, mesh: meshes.len() - 1 })
+                    }
+                    None => None,
+                };
+
+                graph.add(Body {
+                    noise: resolve_noise(body.noise.as_ref(), declared.noise)?,
+                    cloud_noise: resolve_noise(body.cloud_noise.as_ref(), declared.cloud_noise)?,
+                    band_noise: resolve_noise(body.band_noise.as_ref(), declared.band_noise)?,
+                    shader,
+                    name: body.name,
+                    mesh,
+                    scale: body.scale,
+                    position: Vec3::from(body.position),
+                    rotation: Vec3::from(body.rotation),
+                    orbit: body.orbit,
+                    cull_mode: body.cull,
+                    front_face: body.front_face,
+                    textures: body_textures,
+                    light: body.light.map(|light| Emitter {
+                        color: Vec3::from(light.color),
+                        intensity: light.intensity,
+                        falloff: light.falloff,
+                    }),
+                    material: body.material,
+                    // Los transparentes no escriben profundidad salvo que se pida
+                    blend: body.blend.map(|mode| BlendState {
+                        mode,
+                        depth_write: body.depth_write.unwrap_or(false),
+                    }),
+                    atmosphere: body.atmosphere,
+                    rings,
+                }, parent);
+            }
+            systems.push(System { name: system.name, graph });
+        }
+
+        let background = match desc.background {
+            None | Some(BackgroundDesc::Color) => Background::Solid,
+            Some(BackgroundDesc::Starfield(settings)) => Background::Starfield(Starfield::new(settings)),
+            Some(BackgroundDesc::Skybox { faces }) => {
+                let mut loaded = Vec::with_capacity(faces.len());
+                for path in faces {
+                    loaded.push(load_texture(&mut textures, (path, Filter::Bilinear, WrapMode::ClampToEdge, WrapMode::ClampToEdge))?);
+                }
+                Background::Skybox(Skybox {
+                    faces: loaded.try_into().unwrap_or_else(|_| unreachable!("a skybox always has six faces")),
+                })
+            }
+        };
+
+        let post = match (desc.post, desc.bloom) {
+            (Some(stages), _) => PostChain {
+                stages: stages.into_iter().map(PostDesc::into_stage).collect(),
+            },
+            (None, Some(bloom)) => PostChain {
+                stages: vec![PostStage { enabled: bloom.enabled, effect: Box::new(Bloom::new(bloom.effect)) }],
+            },
+            // Una escena sin `[bloom]` ni `[[post]]` se ve igual que antes
+            (None, None) => PostChain::new(),
+        };
+
+        Ok(Scene {
+            background,
+            camera: Camera {
+                eye: Vec3::from(desc.camera.eye),
+                center: Vec3::from(desc.camera.center),
+                up: Vec3::from(desc.camera.up),
+                background: desc.camera.background,
+            },
+            lights: desc.lights.into_iter().map(LightDesc::into_light).collect(),
+            shadows: desc.shadows,
+            tone_mapping: desc.tone_mapping,
+            post,
+            systems,
+            meshes,
+            shell_mesh,
+            shadow_map: None,
+        })
+    }
+
+    // Dibuja el sistema con render diferido: primero la geometría de todos los
+    // cuerpos y luego un único pase de sombreado por píxel visible. Antes, si
+    // hay sombras, se dibuja la profundidad vista desde la luz principal, y el
+    // fondo de la escena.
+    pub fn render_system(&mut self, framebuffer: &mut Framebuffer, uniforms: &Uniforms, index: usize, time: u32) {
+        let Some(system) = self.systems.get(index) else {
+            return;
+        };
+
+        // El fondo va primero; los píxeles sin geometría lo conservan
+        self.background.draw(framebuffer, uniforms);
+
+        let view_projection = uniforms.projection_matrix * uniforms.view_matrix;
+        let nodes = system.graph.nodes();
+        let model_matrices: Vec<Mat4> = nodes.iter()
+            .zip(system.graph.world_matrices(time))
+            .map(|(node, world)| node.body.model_matrix(&world))
+            .collect();
+
+        // Luces de la escena más las de los cuerpos emisores, aunque queden fuera de cámara
+        let mut lights = self.lights.clone();
+        for (node, model_matrix) in nodes.iter().zip(&model_matrices) {
+            if let Some(emitter) = node.body.light {
+                lights.push(emitter.at(Vec3::new(model_matrix[(0, 3)], model_matrix[(1, 3)], model_matrix[(2, 3)])));
+            }
+        }
+
+        // Los cuerpos que emiten luz no proyectan sombra (la luz está dentro de
+        // ellos), ni los transparentes
+        let shadow_map = if self.shadows.enabled {
+            let casters: Vec<(&SceneMesh, &Mat4)> = nodes.iter()
+                .zip(&model_matrices)
+                .filter(|(node, _)| node.body.light.is_none() && node.body.blend.is_none())
+                .map(|(node, model_matrix)| (&self.meshes[node.body.mesh], model_matrix))
+                .collect();
+            render_shadow_map(&mut self.shadow_map, &self.shadows, &casters, &lights, time)
+        } else {
+            None
+        };
+
+        // Los anillos pueden hacer sombra sobre cualquier cuerpo del sistema
+        let rings: Vec<RingPlane> = nodes.iter()
+            .zip(&model_matrices)
+            .filter_map(|(node, model_matrix)| {
+                node.body.rings.as_ref().map(|rings| RingPlane::new(rings.shader.rings.clone(), model_matrix))
+            })
+            .collect();
+
+        let frame = FrameContext { uniforms, lights: &lights, shadow_map: &shadow_map, rings: &rings, time };
+        let mut materials = Vec::new();
+        let mut transparent = Vec::new();
+        for (node, model_matrix) in nodes.iter().zip(&model_matrices) {
+            let body = &node.body;
+            let mesh = &self.meshes[body.mesh];
+
+            let bounds = mesh.bounds.transform(model_matrix);
+            if let (Some(settings), Some(shell_mesh)) = (body.atmosphere, self.shell_mesh) {
+                let shell_mesh = &self.meshes[shell_mesh];
+                let atmosphere = Atmosphere::new(settings, bounds.center, bounds.radius);
+                // La esfera de la malla es poligonal: se agranda un poco para
+                // que cubra toda la atmósfera
+                let scale = atmosphere.radius * 1.05 / shell_mesh.bounds.radius.max(1e-6);
+                let shell_matrix = create_model_matrix(bounds.center - shell_mesh.bounds.center * scale, scale, Vec3::zeros());
+                if shell_mesh.bounds.transform(&shell_matrix).is_visible(&view_projection) {
+                    // Se ordena por su borde más cercano para quedar encima de las nubes
+                    let distance = (bounds.center - uniforms.camera_position).magnitude() - atmosphere.radius;
+                    // Desde dentro de la atmósfera solo se ven las caras traseras de la cáscara
+                    let inside = (uniforms.camera_position - atmosphere.center).magnitude() < atmosphere.radius;
+                    transparent.push(TransparentDraw {
+                        distance,
+                        body,
+                        mesh: shell_mesh,
+                        model_matrix: shell_matrix,
+                        shader: &AtmosphereShader,
+                        blend: BlendState { mode: BlendMode::Add, depth_write: false },
+                        cull_mode: if inside { CullMode::Front } else { CullMode::Back },
+                        front_face: FrontFace::default(),
+                        atmosphere: Some(atmosphere),
+                    });
+                }
+            }
+            if let Some(rings) = &body.rings {
+                let ring_mesh = &self.meshes[rings.mesh];
+                let ring_bounds = ring_mesh.bounds.transform(model_matrix);
+                if ring_bounds.is_visible(&view_projection) {
+                    transparent.push(TransparentDraw {
+                        distance: (ring_bounds.center - uniforms.camera_position).magnitude(),
+                        body,
+                        mesh: ring_mesh,
+                        model_matrix: *model_matrix,
+                        shader: &rings.shader,
+                        blend: BlendState { mode: BlendMode::Normal, depth_write: false },
+                        cull_mode: CullMode::None,
+                        front_face: FrontFace::default(),
+                        atmosphere: None,
+                    });
+                }
+            }
+
+            // Descarta el cuerpo completo si su esfera envolvente queda fuera del frustum
+            if !bounds.is_visible(&view_projection) {
+                continue;
+            }
+            if let Some(blend) = body.blend {
+                transparent.push(TransparentDraw {
+                    distance: (bounds.center - uniforms.camera_position).magnitude(),
+                    body,
+                    mesh,
+                    model_matrix: *model_matrix,
+                    shader: body.shader.as_ref(),
+                    blend,
+                    cull_mode: body.cull_mode,
+                    front_face: body.front_face,
+                    atmosphere: None,
+                });
+                continue;
+            }
+
+            // Cada parte se dibuja como un material distinto del pase diferido
+            for part in &mesh.parts {
+                let body_uniforms = frame.body_uniforms(body, part, model_matrix);
+                render_geometry(framebuffer, &body_uniforms, &part.vertices, materials.len() as u32);
+                materials.push(Material { shader: body.shader.as_ref(), uniforms: body_uniforms });
+            }
+        }
+
+        shade_deferred(framebuffer, &materials, time);
+
+        // Pasada transparente, de atrás hacia adelante, sobre lo opaco ya sombreado
+        transparent.sort_by(|a, b| b.distance.total_cmp(&a.distance));
+        for draw in transparent {
+            for part in &draw.mesh.parts {
+                let mut body_uniforms = frame.body_uniforms(draw.body, part, &draw.model_matrix);
+                body_uniforms.cull_mode = draw.cull_mode;
+                body_uniforms.front_face = draw.front_face;
+                body_uniforms.atmosphere = draw.atmosphere;
+                render_transparent(framebuffer, &body_uniforms, &part.vertices, draw.shader, &draw.blend, time);
+            }
+        }
+    }
+}
+
+// Cuerpo, atmósfera o anillos de la pasada transparente.
+struct TransparentDraw<'a> {
+    distance: f32,
+    body: &'a Body,
+    mesh: &'a SceneMesh,
+    model_matrix: Mat4,
+    shader: &'a dyn FragmentShader,
+    blend: BlendState,
+    cull_mode: CullMode,
+    front_face: FrontFace,
+    atmosphere: Option<Atmosphere>,
+}
+
+// Datos del frame compartidos por todos los cuerpos de un sistema.
+struct FrameContext<'a> {
+    uniforms: &'a Uniforms,
+    lights: &'a [Light],
+    shadow_map: &'a Option<Arc<ShadowMap>>,
+    rings: &'a [RingPlane],
+    time: u32,
+}
+
+impl FrameContext<'_> {
+    fn body_uniforms(&self, body: &Body, part: &MeshPart, model_matrix: &Mat4) -> Uniforms {
+        let mut uniforms = Uniforms::new(self.uniforms.view_matrix, self.uniforms.projection_matrix, self.uniforms.viewport_matrix, self.time);
+        uniforms.noise = body.noise.create();
+        uniforms.cloud_noise = body.cloud_noise.create();
+        uniforms.band_noise = body.band_noise.create();
+        uniforms.model_matrix = *model_matrix;
+        uniforms.normal_matrix = create_normal_matrix(model_matrix);
+        uniforms.cull_mode = body.cull_mode;
+        uniforms.front_face = body.front_face;
+        uniforms.textures = body.textures.clone();
+        uniforms.material = body.material.apply(&part.material);
+        uniforms.lights = self.lights.to_vec();
+        uniforms.shadow_map = self.shadow_map.clone();
+        uniforms.rings = self.rings.to_vec();
+        uniforms
+    }
+}
+
+impl Body {
+    // Transformación relativa al padre: posición más órbita, y rotación.
+    pub fn local_matrix(&self, time: u32) -> Mat4 {
+        let mut translation = self.position;
+        if let Some(orbit) = self.orbit {
+            let angle = time as f32 * orbit.speed + orbit.phase;
+            translation += Vec3::new(orbit.radius * angle.cos(), 0.0, orbit.radius * angle.sin());
+        }
+        create_model_matrix(translation, 1.0, self.rotation)
+    }
+
+    pub fn model_matrix(&self, world: &Mat4) -> Mat4 {
+        world * create_model_matrix(Vec3::new(0.0, 0.0, 0.0), self.scale, Vec3::new(0.0, 0.0, 0.0))
+    }
+}
+
+impl LightDesc {
+    fn into_light(self) -> Light {
+        match self {
+            LightDesc::Ambient { color, intensity } => Light::Ambient {
+                color: Vec3::from(color),
+                intensity,
+            },
+            LightDesc::Directional { direction, color, intensity } => Light::Directional {
+                direction: Vec3::from(direction),
+                color: Vec3::from(color),
+                intensity,
+            },
+            LightDesc::Point { position, color, intensity, falloff } => Light::Point {
+                position: Vec3::from(position),
+                color: Vec3::from(color),
+                intensity,
+                falloff,
+            },
+        }
+    }
+}
+
+type TextureKey = (String, Filter, WrapMode, WrapMode);
+
+// Carga cada OBJ una sola vez, con una parte por material del MTL.
+fn load_mesh(path: &str, meshes: &mut Vec<SceneMesh>, indices: &mut HashMap<String, usize>, textures: &mut HashMap<TextureKey, Arc<Texture>>) -> Result<usize, SceneError> {
+    if let Some(&index) = indices.get(path) {
+        return Ok(index);
+    }
+    let obj = Obj::load_custom_obj(path)
+        .map_err(|error| SceneError::Mesh(path.to_string(), error))?;
+    let bounds = BoundingSphere::from_vertices(&obj.get_vertex_array());
+    let mut parts = Vec::new();
+    for (material_id, vertices) in obj.get_material_groups() {
+        let material = match material_id.and_then(|id| obj.materials().get(id)) {
+            Some(material) => SurfaceMaterial {
+                model: LightingModel::BlinnPhong,
+                diffuse: material.diffuse,
+                specular: material.specular,
+                shininess: material.shininess,
+                diffuse_texture: match &material.diffuse_texture {
+                    Some(path) => Some(load_texture(textures, (path.clone(), Filter::default(), WrapMode::default(), WrapMode::default()))?),
+                    None => None,
+                },
+                ..SurfaceMaterial::default()
+            },
+            None => SurfaceMaterial::default(),
+        };
+        parts.push(MeshPart { vertices, material });
+    }
+    meshes.push(SceneMesh { parts, bounds });
+    indices.insert(path.to_string(), meshes.len() - 1);
+    Ok(meshes.len() - 1)
+}
+
+// Carga cada combinación de imagen y muestreo una sola vez.
+fn load_texture(cache: &mut HashMap<TextureKey, Arc<Texture>>, key: TextureKey) -> Result<Arc<Texture>, SceneError> {
+    if let Some(texture) = cache.get(&key) {
+        return Ok(texture.clone());
+    }
+    let mut loaded = Texture::load(&key.0)
+        .map_err(|error| SceneError::Texture(key.0.clone(), error))?;
+    loaded.filter = key.1;
+    loaded.wrap_u = key.2;
+    loaded.wrap_v = key.3;
+    let texture = Arc::new(loaded);
+    cache.insert(key, texture.clone());
+    Ok(texture)
+}
+
+// Profundidad de los cuerpos vista desde la luz principal. El mapa se reutiliza
+// entre frames (los uniforms del frame anterior ya lo soltaron) mientras no
+// cambie la resolución configurada.
+fn render_shadow_map(slot: &mut Option<Arc<ShadowMap>>, settings: &ShadowSettings, casters: &[(&SceneMesh, &Mat4)], lights: &[Light], time: u32) -> Option<Arc<ShadowMap>> {
+    let mut shadow_map = slot.take()
+        .and_then(|shadow_map| Arc::try_unwrap(shadow_map).ok())
+        .filter(|shadow_map| shadow_map.resolution == settings.resolution.max(1))
+        .unwrap_or_else(|| ShadowMap::new(settings));
+    shadow_map.pcf_radius = settings.pcf_radius.max(0);
+    shadow_map.normal_offset = settings.normal_offset;
+
+    let bounds: Vec<BoundingSphere> = casters.iter()
+        .map(|(mesh, model_matrix)| mesh.bounds.transform(model_matrix))
+        .collect();
+    let ready = shadow_map.prepare(lights, &bounds);
+    if ready {
+        for (mesh, model_matrix) in casters {
+            let mut light_uniforms = Uniforms::new(shadow_map.view_matrix, shadow_map.projection_matrix, shadow_map.viewport_matrix, time);
+            light_uniforms.model_matrix = **model_matrix;
+            light_uniforms.cull_mode = CullMode::None;
+            for part in &mesh.parts {
+                render_depth(&mut shadow_map.depth, shadow_map.resolution, shadow_map.resolution, &light_uniforms, &part.vertices);
+            }
+        }
+    }
+
+    let shadow_map = Arc::new(shadow_map);
+    *slot = Some(shadow_map.clone());
+    ready.then_some(shadow_map)
+}
+
+// Sin descripción se usa el ruido que declara el shader.
+fn resolve_noise(desc: Option<&NoiseDesc>, declared: NoiseSettings) -> Result<NoiseSettings, SceneError> {
+    let preset = |name: &str| {
+        NoiseSettings::preset(name).ok_or_else(|| SceneError::UnknownNoise(name.to_string()))
+    };
+
+    match desc {
+        None => Ok(declared),
+        Some(NoiseDesc::Preset(name)) => preset(name),
+        Some(NoiseDesc::Custom(params)) => {
+            let mut settings = match &params.preset {
+                Some(name) => preset(name)?,
+                None => NoiseSettings::default(),
+            };
+            if let Some(name) = &params.noise_type {
+                settings.noise_type = Some(
+                    parse_noise_type(name).ok_or_else(|| SceneError::UnknownNoise(name.clone()))?,
+                );
+            }
+            if let Some(name) = &params.fractal_type {
+                settings.fractal_type = Some(
+                    parse_fractal_type(name).ok_or_else(|| SceneError::UnknownNoise(name.clone()))?,
+                );
+            }
+            settings.seed = params.seed.or(settings.seed);
+            settings.octaves = params.octaves.or(settings.octaves);
+            settings.lacunarity = params.lacunarity.or(settings.lacunarity);
+            settings.gain = params.gain.or(settings.gain);
+            settings.frequency = params.frequency.or(settings.frequency);
+            Ok(settings)
+        }
+    }
+}
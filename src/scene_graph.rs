@@ -0,0 +1,67 @@
+use nalgebra_glm::Mat4;
+use crate::scene::Body;
+
+// Grafo de escena: cada nodo tiene una transformación local relativa a su padre
+// (sol -> planeta -> luna). Las matrices de mundo se componen en cada frame al
+// recorrer el grafo.
+//
+// Los hijos heredan la posición, la órbita y la rotación del padre, pero no su
+// escala, para que el tamaño de una luna no dependa del de su planeta.
+pub struct SceneNode {
+    pub body: Body,
+    pub parent: Option<usize>,
+    pub children: Vec<usize>,
+}
+
+#[derive(Default)]
+pub struct SceneGraph {
+    nodes: Vec<SceneNode>,
+    roots: Vec<usize>,
+}
+
+impl SceneGraph {
+    pub fn new() -> Self {
+        SceneGraph::default()
+    }
+
+    pub fn add(&mut self, body: Body, parent: Option<usize>) -> usize {
+        let index = self.nodes.len();
+        match parent {
+            Some(parent) => self.nodes[parent].children.push(index),
+            None => self.roots.push(index),
+        }
+        self.nodes.push(SceneNode { body, parent, children: Vec::new() });
+        index
+    }
+
+    pub fn find(&self, name: &str) -> Option<usize> {
+        self.nodes.iter().position(|node| node.body.name == name)
+    }
+
+    pub fn nodes(&self) -> &[SceneNode] {
+        &self.nodes
+    }
+
+    // Matriz de mundo (sin la escala propia del cuerpo) de cada nodo.
+    pub fn world_matrices(&self, time: u32) -> Vec<Mat4> {
+        let mut worlds = vec![Mat4::identity(); self.nodes.len()];
+        self.walk(time, |index, world| worlds[index] = *world);
+        worlds
+    }
+
+    // Recorre el grafo en profundidad, padres antes que hijos.
+    pub fn walk<F: FnMut(usize, &Mat4)>(&self, time: u32, mut visit: F) {
+        let mut stack: Vec<(usize, Mat4)> = self.roots.iter().rev()
+            .map(|&root| (root, Mat4::identity()))
+            .collect();
+
+        while let Some((index, parent_world)) = stack.pop() {
+            let node = &self.nodes[index];
+            let world = parent_world * node.body.local_matrix(time);
+            visit(index, &world);
+            for &child in node.children.iter().rev() {
+                stack.push((child, world));
+            }
+        }
+    }
+}
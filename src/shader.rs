@@ -1,9 +1,17 @@
-use nalgebra_glm::{Vec2,Vec3, Vec4, dot, normalize, smoothstep};
+use nalgebra_glm::{Vec2,Vec3, Vec4, smoothstep};
 use crate::vertex::Vertex;
 use crate::render::Uniforms;
 use crate::color::Color;
 use crate::fragment::Fragment;
-
+use crate::light::diffuse_lighting;
+use crate::material::{shade_surface, surface_radiance};
+use crate::noise::{NoiseSettings, CLOUD_NOISE, KENSHI_NOISE, MOON_NOISE, RATCHET_NOISE, RATCHET_T_NOISE, ROCKY_NOISE, SIMPLE_NOISE};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+// Lleva el vértice a espacio de clip. La división por `w` y el viewport se
+// aplican después del recorte (ver `clipping`). La normal y la posición salen
+// en espacio de mundo para la iluminación.
 pub fn vertex_shader(vertex: &Vertex, uniforms: &Uniforms) -> Vertex {
     let position = Vec4::new(
         vertex.position.x,
@@ -11,45 +19,222 @@ pub fn vertex_shader(vertex: &Vertex, uniforms: &Uniforms) -> Vertex {
         vertex.position.z,
         1.0
     );
-        let transformed = uniforms.viewport_matrix * uniforms.projection_matrix * uniforms.view_matrix * uniforms.model_matrix * position;
-        let w = transformed.w;
-    let transformed_position = Vec3::new(
-        transformed.x / w,
-        transformed.y / w,
-        transformed.z / w
-    );
+    let world = uniforms.model_matrix * position;
+    let clip_position = uniforms.projection_matrix * uniforms.view_matrix * world;
+    let transformed_normal = (uniforms.normal_matrix * vertex.normal).normalize();
     Vertex {
         position: vertex.position,
         normal: vertex.normal,
         tex_coords: vertex.tex_coords,
         color: vertex.color,
-        transformed_position,
-        transformed_normal: vertex.normal,
+        transformed_position: vertex.position,
+        transformed_normal,
+        world_position: Vec3::new(world.x, world.y, world.z),
+        clip_position,
     }
 }
 
-pub fn fragment_shader(fragment: &Fragment, uniforms: &Uniforms, time: u32) -> (Color, u32) {
-  match uniforms.current_shader {
-      1 => kenshi_shader(fragment, uniforms, time),
-      2 => moon_shader(fragment, uniforms),
-      3 => ratchet_toxic_shader(fragment, uniforms, time),
-      4 => rocky_planet_shader(fragment, uniforms, time),
-      5 => ratchet_shader(fragment, uniforms, time as f32),
-      6 => ratchet1_shader(fragment, uniforms, time),
-      7 => sun_shader(time),
-      8 => simple_planet_shader(fragment, uniforms),
-      _ => (Color::new(0, 0, 0), 0),
-  }
+// Un shader de fragmentos con nombre. Cada implementación declara los ruidos
+// que espera encontrar en los uniforms; el archivo de escena puede reemplazarlos.
+//
+// `shade` devuelve el color de la superficie y la luz que emite (RGB lineal,
+// negro si no emite). La emisión se suma al color y alimenta el bloom.
+pub trait FragmentShader: Send + Sync {
+    fn noise(&self) -> ShaderNoise {
+        ShaderNoise::default()
+    }
+
+    fn shade(&self, fragment: &Fragment, uniforms: &Uniforms, time: u32) -> (Color, Color);
 }
 
-pub fn simple_planet_shader(fragment: &Fragment, uniforms: &Uniforms) -> (Color, u32) {
-    let (base_color, detail_color) = match uniforms.current_shader {
-        1 => (Color::from_float(0.6, 0.4, 0.2), Color::from_float(0.4, 0.3, 0.1)), 
-        2 => (Color::from_float(0.2, 0.6, 0.3), Color::from_float(0.1, 0.4, 0.2)),
-        3 => (Color::from_float(0.2, 0.3, 0.6), Color::from_float(0.1, 0.2, 0.5)),
-        _ => (Color::from_float(0.8, 0.8, 0.8), Color::from_float(0.5, 0.5, 0.5)), 
-    };
+#[derive(Clone, Copy, Debug)]
+pub struct ShaderNoise {
+    pub noise: NoiseSettings,
+    pub cloud_noise: NoiseSettings,
+    pub band_noise: NoiseSettings,
+}
+
+impl ShaderNoise {
+    fn with_noise(noise: NoiseSettings) -> Self {
+        ShaderNoise { noise, ..Default::default() }
+    }
+}
+
+impl Default for ShaderNoise {
+    fn default() -> Self {
+        ShaderNoise {
+            noise: NoiseSettings::default(),
+            cloud_noise: CLOUD_NOISE,
+            band_noise: NoiseSettings::default(),
+        }
+    }
+}
+
+// Shaders disponibles por nombre. Se pueden registrar shaders propios antes de
+// cargar una escena.
+#[derive(Default)]
+pub struct ShaderRegistry {
+    shaders: HashMap<String, Arc<dyn FragmentShader>>,
+}
+
+impl ShaderRegistry {
+    pub fn new() -> Self {
+        ShaderRegistry::default()
+    }
+
+    pub fn with_builtin() -> Self {
+        let mut registry = ShaderRegistry::new();
+        registry.register("kenshi", KenshiShader);
+        registry.register("moon", MoonShader {
+            base_color: Color::from_srgb(0.4, 0.4, 0.4),
+            detail_color: Color::from_srgb(0.2, 0.2, 0.2),
+        });
+        registry.register("ratchet_toxic", RatchetToxicShader);
+        registry.register("rocky", RockyPlanetShader);
+        registry.register("ratchet", RatchetShader);
+        registry.register("ratchet1", Ratchet1Shader);
+        registry.register("sun", SunShader);
+        registry.register("textured", TexturedShader);
+        registry.register("material", MaterialShader);
+        registry.register("clouds", CloudShader);
+        registry.register("simple", SimplePlanetShader {
+            base_color: Color::from_srgb(0.8, 0.8, 0.8),
+            detail_color: Color::from_srgb(0.5, 0.5, 0.5),
+        });
+        registry
+    }
+
+    pub fn register<S: FragmentShader + 'static>(&mut self, name: &str, shader: S) {
+        self.shaders.insert(name.to_string(), Arc::new(shader));
+    }
+
+    pub fn get(&self, name: &str) -> Option<Arc<dyn FragmentShader>> {
+        self.shaders.get(name).cloned()
+    }
+}
+
+pub struct KenshiShader;
+
+impl FragmentShader for KenshiShader {
+    fn noise(&self) -> ShaderNoise {
+        ShaderNoise::with_noise(KENSHI_NOISE)
+    }
+
+    fn shade(&self, fragment: &Fragment, uniforms: &Uniforms, time: u32) -> (Color, Color) {
+        kenshi_shader(fragment, uniforms, time)
+    }
+}
+
+pub struct MoonShader {
+    pub base_color: Color,
+    pub detail_color: Color,
+}
+
+impl FragmentShader for MoonShader {
+    fn noise(&self) -> ShaderNoise {
+        ShaderNoise::with_noise(MOON_NOISE)
+    }
+
+    fn shade(&self, fragment: &Fragment, uniforms: &Uniforms, _time: u32) -> (Color, Color) {
+        moon_shader(fragment, uniforms, self.base_color, self.detail_color)
+    }
+}
+
+pub struct RatchetToxicShader;
+
+impl FragmentShader for RatchetToxicShader {
+    fn noise(&self) -> ShaderNoise {
+        ShaderNoise::with_noise(RATCHET_T_NOISE)
+    }
+
+    fn shade(&self, fragment: &Fragment, uniforms: &Uniforms, time: u32) -> (Color, Color) {
+        ratchet_toxic_shader(fragment, uniforms, time)
+    }
+}
+
+pub struct RockyPlanetShader;
+
+impl FragmentShader for RockyPlanetShader {
+    fn noise(&self) -> ShaderNoise {
+        ShaderNoise::with_noise(ROCKY_NOISE)
+    }
+
+    fn shade(&self, fragment: &Fragment, uniforms: &Uniforms, time: u32) -> (Color, Color) {
+        rocky_planet_shader(fragment, uniforms, time)
+    }
+}
+
+pub struct RatchetShader;
 
+impl FragmentShader for RatchetShader {
+    fn noise(&self) -> ShaderNoise {
+        ShaderNoise::with_noise(RATCHET_NOISE)
+    }
+
+    fn shade(&self, fragment: &Fragment, uniforms: &Uniforms, time: u32) -> (Color, Color) {
+        ratchet_shader(fragment, uniforms, time as f32)
+    }
+}
+
+pub struct Ratchet1Shader;
+
+impl FragmentShader for Ratchet1Shader {
+    fn noise(&self) -> ShaderNoise {
+        ShaderNoise::with_noise(SIMPLE_NOISE)
+    }
+
+    fn shade(&self, fragment: &Fragment, uniforms: &Uniforms, time: u32) -> (Color, Color) {
+        ratchet1_shader(fragment, uniforms, time)
+    }
+}
+
+pub struct SunShader;
+
+impl FragmentShader for SunShader {
+    fn shade(&self, _fragment: &Fragment, _uniforms: &Uniforms, time: u32) -> (Color, Color) {
+        sun_shader(time)
+    }
+}
+
+pub struct SimplePlanetShader {
+    pub base_color: Color,
+    pub detail_color: Color,
+}
+
+impl FragmentShader for SimplePlanetShader {
+    fn shade(&self, fragment: &Fragment, uniforms: &Uniforms, _time: u32) -> (Color, Color) {
+        simple_planet_shader(fragment, uniforms, self.base_color, self.detail_color)
+    }
+}
+
+// Usa la primera textura del cuerpo como color base.
+pub struct TexturedShader;
+
+impl FragmentShader for TexturedShader {
+    fn shade(&self, fragment: &Fragment, uniforms: &Uniforms, _time: u32) -> (Color, Color) {
+        textured_shader(fragment, uniforms)
+    }
+}
+
+// Colorea con el material del MTL de la malla (Kd, Ks, Ns y map_Kd).
+pub struct MaterialShader;
+
+impl FragmentShader for MaterialShader {
+    fn shade(&self, fragment: &Fragment, uniforms: &Uniforms, _time: u32) -> (Color, Color) {
+        material_shader(fragment, uniforms)
+    }
+}
+
+// Capa de nubes semitransparente para dibujar sobre un planeta (con `blend`).
+pub struct CloudShader;
+
+impl FragmentShader for CloudShader {
+    fn shade(&self, fragment: &Fragment, uniforms: &Uniforms, time: u32) -> (Color, Color) {
+        cloud_shader(fragment, uniforms, time)
+    }
+}
+
+pub fn simple_planet_shader(fragment: &Fragment, uniforms: &Uniforms, base_color: Color, detail_color: Color) -> (Color, Color) {
     let noise_value = uniforms.noise.get_noise_2d(
         fragment.vertex_position.x * 50.0, 
         fragment.vertex_position.y * 50.0
@@ -58,15 +243,10 @@ pub fn simple_planet_shader(fragment: &Fragment, uniforms: &Uniforms) -> (Color,
 
     let surface_color = base_color.lerp(&detail_color, normalized_noise.clamp(0.0, 1.0));
 
-    let light_position = Vec3::new(5.0, 5.0, 5.0); 
-    let light_direction = (light_position - fragment.vertex_position).normalize(); 
-    let normal = fragment.normal.normalize(); // Normal del fragmento
-    let diffuse = normal.dot(&light_direction).max(0.0); // Cálculo de iluminación difusa
-
-    (surface_color * (0.3 + 0.7 * diffuse), 0)
+    (shade_surface(uniforms, fragment, surface_color, 1.0), Color::black())
 }
 
-fn rocky_planet_shader(fragment: &Fragment, uniforms: &Uniforms, time: u32) -> (Color, u32) {
+pub fn rocky_planet_shader(fragment: &Fragment, uniforms: &Uniforms, time: u32) -> (Color, Color) {
     let x = fragment.vertex_position.x;
     let y = fragment.vertex_position.y;
     let t = time as f32 * 0.1;
@@ -74,9 +254,9 @@ fn rocky_planet_shader(fragment: &Fragment, uniforms: &Uniforms, time: u32) -> (
     let base_noise_value = uniforms.noise.get_noise_2d(x, y);
     let rock_noise_value = uniforms.noise.get_noise_3d(x - t, x+y -t, y );
 
-    let rocky_color_1 = Color::from_float(0.6, 0.5, 0.4); // Color principal rocoso
-    let rocky_color_2 = Color::from_float(0.4, 0.3, 0.2); // Color de zonas más oscuras y rocosas
-    let rocky_color_3 = Color::from_float(0.2, 0.1, 0.05); // Color de las zonas más áridas y secas
+    let rocky_color_1 = Color::from_srgb(0.6, 0.5, 0.4); // Color principal rocoso
+    let rocky_color_2 = Color::from_srgb(0.4, 0.3, 0.2); // Color de zonas más oscuras y rocosas
+    let rocky_color_3 = Color::from_srgb(0.2, 0.1, 0.05); // Color de las zonas más áridas y secas
 
     let land_threshold = 0.4;
 
@@ -91,26 +271,27 @@ fn rocky_planet_shader(fragment: &Fragment, uniforms: &Uniforms, time: u32) -> (
         rocky_color_1.lerp(&rocky_color_2, base_noise_value / land_threshold)
     };
 
-    // Calculamos la iluminación basada en la posición de la luz
-    let light_position = Vec3::new(1.0, 1.0, 3.0); // Posición de la fuente de luz
-    let light_dir = normalize(&(light_position - fragment.vertex_position)); // Dirección de la luz
-    let normal = normalize(&fragment.normal); // Normal del fragmento para iluminación
-    let diffuse = dot(&normal, &light_dir).max(0.0); // Cálculo de la iluminación difusa
+    // Calculamos la iluminación con las luces de la escena
+    let lit_color = shade_surface(uniforms, fragment, base_color, 1.0);
 
-    let lit_color = base_color * (0.1 + 0.9 * diffuse);
+    // Grietas de lava en el borde de las zonas rocosas, con un parpadeo lento
+    let seam_width = 0.04;
+    let seam = 1.0 - smoothstep(0.0, seam_width, (base_noise_value - land_threshold).abs());
+    let flicker = 0.8 + 0.2 * (t * 0.3 + rock_noise_value * 4.0).sin();
+    let lava = Color::from_srgb(1.0, 0.35, 0.05) * (seam * flicker * 3.0);
 
     let dust_threshold = 0.3; 
     let dust_opacity = 0.2 + 0.1 * ((time as f32 / 500.0) * 0.5).sin().abs(); 
     if rock_noise_value > dust_threshold {
         let dust_intensity = ((rock_noise_value - dust_threshold) / (1.0 - dust_threshold)).clamp(0.0, 1.0);
-        (lit_color.blend_add(&(rocky_color_3 * (dust_intensity * dust_opacity))), 0)
+        (lit_color.blend_add(&(rocky_color_3 * (dust_intensity * dust_opacity))), lava)
     } else {
-        (lit_color, 0)
+        (lit_color, lava)
     }
 }
 
 
-pub fn ratchet_toxic_shader(fragment: &Fragment, uniforms: &Uniforms, time: u32) -> (Color, u32) {
+pub fn ratchet_toxic_shader(fragment: &Fragment, uniforms: &Uniforms, time: u32) -> (Color, Color) {
     let zoom = 100.0;  
     let ox = 100.0; 
     let oy = 100.0;
@@ -125,15 +306,15 @@ pub fn ratchet_toxic_shader(fragment: &Fragment, uniforms: &Uniforms, time: u32)
     let offset_x = t * 0.1; 
     let offset_y = t * 0.05; 
     let cloud_noise_value = uniforms.cloud_noise.get_noise_2d(
-        (x * zoom + ox + t + offset_x), 
-        (y * zoom + oy + offset_y + t)
+        x * zoom + ox + t + offset_x,
+        y * zoom + oy + offset_y + t
     );
 
-    let land_color_1 = Color::from_float(0.1, 0.3, 0.0); // Verde tóxico 1
-    let land_color_2 = Color::from_float(0.2, 0.5, 0.1); // Verde tóxico 2
+    let land_color_1 = Color::from_srgb(0.1, 0.3, 0.0); // Verde tóxico 1
+    let land_color_2 = Color::from_srgb(0.2, 0.5, 0.1); // Verde tóxico 2
 
-    let cloud_color = Color::from_float(0.9, 0.6, 0.2); // Naranja para la atmósfera
-    let atmosphere_color = Color::from_float(0.1, 0.4, 0.3); // Verde azulado para otra capa de atmósfera
+    let cloud_color = Color::from_srgb(0.9, 0.6, 0.2); // Naranja para la atmósfera
+    let atmosphere_color = Color::from_srgb(0.1, 0.4, 0.3); // Verde azulado para otra capa de atmósfera
 
     let land_threshold = 0.3;
 
@@ -148,12 +329,7 @@ pub fn ratchet_toxic_shader(fragment: &Fragment, uniforms: &Uniforms, time: u32)
         land_color_1.lerp(&land_color_2, base_noise_value / land_threshold)
     };
 
-    let light_position = Vec3::new(1.0, 1.0, 3.0); 
-    let light_dir = normalize(&(light_position - fragment.vertex_position));
-    let normal = normalize(&fragment.normal); 
-    let diffuse = dot(&normal, &light_dir).max(0.0);
-
-    let lit_color = base_color * (0.1 + 0.9 * diffuse); 
+    let lit_color = shade_surface(uniforms, fragment, base_color, 1.0);
 
     let cloud_threshold = 0.25; 
     let cloud_opacity = 0.3 + 0.2 * ((time as f32 / 1000.0) * 0.3).sin().abs(); 
@@ -163,14 +339,14 @@ pub fn ratchet_toxic_shader(fragment: &Fragment, uniforms: &Uniforms, time: u32)
         let cloud_layer = cloud_color * (cloud_intensity * cloud_opacity);
         let atmosphere_intensity = (cloud_noise_value * 0.5).clamp(0.0, 1.0);
         let atmosphere_layer = atmosphere_color * atmosphere_intensity;
-        (lit_color.blend_add(&cloud_layer).blend_add(&atmosphere_layer), 0)
+        (lit_color.blend_add(&cloud_layer).blend_add(&atmosphere_layer), Color::black())
     } else {
-        (lit_color, 0)
+        (lit_color, Color::black())
     }
 }
 
 
-pub fn ratchet_shader(fragment: &Fragment, uniforms: &Uniforms, time: f32) -> (Color, u32) {
+pub fn ratchet_shader(fragment: &Fragment, uniforms: &Uniforms, time: f32) -> (Color, Color) {
     // Capa 1: Bandas horizontales difuminadas
     let latitude = fragment.vertex_position.y;
     let band_frequency = 8.0;
@@ -250,20 +426,11 @@ pub fn ratchet_shader(fragment: &Fragment, uniforms: &Uniforms, time: f32) -> (C
     let final_color = variation_color.lerp(&spot_color, spot_intensity * 0.85);
 
     // Iluminación
-    let light_position = Vec3::new(10.0, 10.0, 20.0);
-    let light_direction = (light_position - fragment.vertex_position).normalize();
-    let normal = fragment.normal.normalize();
-    let diffuse = normal.dot(&light_direction).max(0.0);
-    let ambient_intensity = 0.2;
-    let ambient_color = final_color * ambient_intensity;
-    let lit_color = final_color * diffuse;
-    let color_with_lighting = ambient_color + lit_color;
-
-    (color_with_lighting, 0)
+    (shade_surface(uniforms, fragment, final_color, 1.0), Color::black())
 }
 
 
-pub fn ratchet1_shader(fragment: &Fragment, uniforms: &Uniforms, time: u32) -> (Color, u32) {
+pub fn ratchet1_shader(fragment: &Fragment, uniforms: &Uniforms, time: u32) -> (Color, Color) {
   let x = fragment.vertex_position.x;
   let y = fragment.vertex_position.y;
   let z = fragment.vertex_position.z;
@@ -271,23 +438,19 @@ pub fn ratchet1_shader(fragment: &Fragment, uniforms: &Uniforms, time: u32) -> (
 
   let noise_value = uniforms.noise.get_noise_3d(x, y + t, z);
 
-  let base_color = Color::from_float(0.9, 0.3, 0.9);
+  let base_color = Color::from_srgb(0.9, 0.3, 0.9);
 
   let intensity = (noise_value * 0.5 + 0.5).clamp(0.0, 1.0);
   let varied_color = base_color * intensity;
 
-  let light_dir = Vec3::new(1.0, 1.0, 1.0).normalize();
-  let normal = fragment.normal.normalize(); 
-  let diffuse = normal.dot(&light_dir).max(0.0); 
-  let ambient = 0.3; 
-  let lit_color = varied_color * (ambient + (1.0 - ambient) * diffuse); 
+  let lit_color = shade_surface(uniforms, fragment, varied_color, 1.0);
 
-  (lit_color, 0)
+  (lit_color, Color::black())
 }
 
-pub fn sun_shader(time: u32) -> (Color, u32) {
-    // Base color del Sol
-    let base_color = Color::from_float(1.0, 0.8, 0.3);
+pub fn sun_shader(time: u32) -> (Color, Color) {
+    // Base color del Sol (#FFCC4D en sRGB, guardado en lineal)
+    let base_color = Color::new(255, 204, 77);
 
     // Factores de ruido para las tormentas solares
     let frequency = 0.03; // Frecuencia de las variaciones
@@ -295,41 +458,30 @@ pub fn sun_shader(time: u32) -> (Color, u32) {
     let noise_g = ((time as f32 * frequency * 1.3).cos() * 0.5 + 0.5) * 0.2; // Variación en verde
     let noise_b = ((time as f32 * frequency * 1.7).sin() * 0.5 + 0.5) * 0.1; // Variación en azul
 
-    // Patrón dinámico: alterna entre tonos cálidos (amarillos y naranjas)
-    let r = (base_color.r as f32 / 255.0 + noise_r).min(1.0);
-    let g = (base_color.g as f32 / 255.0 + noise_g).min(1.0);
-    let b = (base_color.b as f32 / 255.0 + noise_b).min(1.0);
+    // Patrón dinámico: alterna entre tonos cálidos (amarillos y naranjas). Las
+    // variaciones se suman en sRGB y el resultado vuelve a lineal al final
+    let base_srgb = base_color.to_srgb();
+    let r = base_srgb.r + noise_r;
+    let g = base_srgb.g + noise_g;
+    let b = base_srgb.b + noise_b;
 
     // Intensidad de emisión ajustada para simular destellos de tormentas solares
-    let emission_base = 150;
-    let emission_variation = (50.0 * ((time as f32 * 0.02).cos() * 0.5 + 0.5)) as u32;
-    let emission = emission_base + emission_variation;
-
-    // Crear el color final
-    let dynamic_color = Color::from_float(r, g, b);
+    let emission = 1.5 + 0.5 * ((time as f32 * 0.02).cos() * 0.5 + 0.5);
 
-    (dynamic_color, emission)
+    // El sol no refleja luz: todo su color es emisión, por encima de 1.0 (HDR);
+    // el tone mapping lo comprime sin aplanarlo a blanco
+    (Color::black(), Color::from_srgb(r, g, b) * emission)
 }
 
-pub fn moon_shader(fragment: &Fragment, uniforms: &Uniforms) -> (Color, u32) {
-    let (base_color, detail_color) = match uniforms.current_shader {
-        1 => (Color::from_float(0.1, 0.2, 0.4), Color::from_float(0.05, 0.05, 0.1)), // Azul oscuro
-        2 => (Color::from_float(0.4, 0.4, 0.4), Color::from_float(0.2, 0.2, 0.2)), // Gris medio
-        3 => (Color::from_float(0.2, 0.2, 0.2), Color::from_float(0.05, 0.05, 0.05)), // Gris oscuro
-        _ => (Color::from_float(0.8, 0.8, 0.8), Color::from_float(0.3, 0.3, 0.3)),    // Por defecto: tonos claros
-    };
+pub fn moon_shader(fragment: &Fragment, uniforms: &Uniforms, base_color: Color, detail_color: Color) -> (Color, Color) {
     let noise_value = uniforms.noise.get_noise_2d(fragment.vertex_position.x, fragment.vertex_position.y);
     let normalized_noise = (noise_value + 1.0) * 0.5; 
     let surface_variation = base_color.lerp(&detail_color, normalized_noise.clamp(0.0, 1.0));
-    let light_position = Vec3::new(10.0, 10.0, 10.0); // Fuente de luz
-    let light_direction = (light_position - fragment.vertex_position).normalize();
-    let normal = fragment.normal.normalize();
-    let diffuse = normal.dot(&light_direction).max(0.0);
-    (surface_variation * (0.2 + 0.6 * diffuse), 0)
+    (shade_surface(uniforms, fragment, surface_variation, 1.0), Color::black())
 }
 
 
-fn kenshi_shader(fragment: &Fragment, uniforms: &Uniforms, time: u32) -> (Color, u32) {
+pub fn kenshi_shader(fragment: &Fragment, uniforms: &Uniforms, time: u32) -> (Color, Color) {
   let zoom = 100.0;  
   let ox = 100.0; 
   let oy = 100.0;
@@ -341,16 +493,16 @@ fn kenshi_shader(fragment: &Fragment, uniforms: &Uniforms, time: u32) -> (Color,
   let offset_x = t * 0.1; 
   let offset_y = t * 0.05; 
   let cloud_noise_value = uniforms.cloud_noise.get_noise_2d(
-      (x * zoom + ox +t + offset_x), (y * zoom + oy + offset_y + t)
+      x * zoom + ox + t + offset_x, y * zoom + oy + offset_y + t
   );
 
 
-  let water_color_1 = Color::from_float(0.0, 0.1, 0.6); 
-  let water_color_2 = Color::from_float(0.0, 0.3, 0.7);
-  let land_color_1 = Color::from_float(0.2, 0.4, 0.0); 
-  let land_color_2 = Color::from_float(0.6, 0.5, 0.2);
-  let land_color_3 = Color::from_float(0.4, 0.3, 0.1);
-  let cloud_color = Color::from_float(0.9, 0.9, 0.9); 
+  let water_color_1 = Color::from_srgb(0.0, 0.1, 0.6); 
+  let water_color_2 = Color::from_srgb(0.0, 0.3, 0.7);
+  let land_color_1 = Color::from_srgb(0.2, 0.4, 0.0); 
+  let land_color_2 = Color::from_srgb(0.6, 0.5, 0.2);
+  let land_color_3 = Color::from_srgb(0.4, 0.3, 0.1);
+  let cloud_color = Color::from_srgb(0.9, 0.9, 0.9); 
 
   let land_threshold = 0.3; 
 
@@ -365,19 +517,63 @@ fn kenshi_shader(fragment: &Fragment, uniforms: &Uniforms, time: u32) -> (Color,
     water_color_1.lerp(&water_color_2, base_noise_value / land_threshold)
     };
 
-  let light_position = Vec3::new(1.0, 1.0, 3.0); 
-  let light_dir = normalize(&(light_position - fragment.vertex_position));
-  let normal = normalize(&fragment.normal); 
-  let diffuse = dot(&normal, &light_dir).max(0.0);
-
-  let lit_color = base_color * (0.1 + 0.9 * diffuse); 
+  // Solo el agua tiene brillo especular
+  let specular_mask = if base_noise_value > land_threshold { 0.0 } else { 1.0 };
+  let lit_color = shade_surface(uniforms, fragment, base_color, specular_mask);
 
   let cloud_threshold = 0.25; 
   let cloud_opacity = 0.3 + 0.2 * ((time as f32 / 1000.0) * 0.3).sin().abs(); 
+  let cloud_intensity = ((cloud_noise_value - cloud_threshold) / (1.0 - cloud_threshold)).clamp(0.0, 1.0);
+
+  // Luces de ciudades en tierra firme, solo del lado de noche y tapadas por las nubes
+  let city_lights = if base_noise_value > land_threshold {
+      let daylight = diffuse_lighting(uniforms, &fragment.world_position, &fragment.normal).max();
+      let night = 1.0 - smoothstep(0.05, 0.2, daylight);
+      // Regiones pobladas (ruido grueso) salpicadas de ciudades (ruido fino)
+      let p = fragment.vertex_position * 8.0;
+      let regions = smoothstep(0.3, 0.6, uniforms.noise.get_noise_3d(p.x, p.y, p.z));
+      let p = fragment.vertex_position * 40.0;
+      let cities = smoothstep(0.7, 0.9, uniforms.noise.get_noise_3d(p.x, p.y, p.z)) * regions;
+      Color::from_srgb(1.0, 0.7, 0.35) * (cities * night * (1.0 - cloud_intensity) * 2.0)
+  } else {
+      Color::black()
+  };
+
   if cloud_noise_value > cloud_threshold {
-      let cloud_intensity = ((cloud_noise_value - cloud_threshold) / (1.0 - cloud_threshold)).clamp(0.0, 1.0);
-      (lit_color.blend_add(&(cloud_color * (cloud_intensity * cloud_opacity))), 0)
+      (lit_color.blend_add(&(cloud_color * (cloud_intensity * cloud_opacity))), city_lights)
   } else {
-      (lit_color, 0)
+      (lit_color, city_lights)
   }
 }
+
+pub fn textured_shader(fragment: &Fragment, uniforms: &Uniforms) -> (Color, Color) {
+    let surface_color = match (uniforms.textures.first(), fragment.uv) {
+        (Some(texture), Some(uv)) => texture.sample(uv, fragment.uv_footprint),
+        _ => Color::from_srgb(0.8, 0.8, 0.8),
+    };
+
+    (shade_surface(uniforms, fragment, surface_color, 1.0), Color::black())
+}
+
+pub fn material_shader(fragment: &Fragment, uniforms: &Uniforms) -> (Color, Color) {
+    let material = &uniforms.material;
+    let mut albedo = material.diffuse;
+    if let (Some(texture), Some(uv)) = (&material.diffuse_texture, fragment.uv) {
+        albedo = albedo.component_mul(&texture.sample_vec3(uv, fragment.uv_footprint));
+    }
+
+    let color = surface_radiance(uniforms, fragment, albedo, 1.0);
+    (Color::from_float(color.x, color.y, color.z), Color::black())
+}
+
+pub fn cloud_shader(fragment: &Fragment, uniforms: &Uniforms, time: u32) -> (Color, Color) {
+    let drift = time as f32 * 0.05;
+    let position = fragment.vertex_position * 300.0;
+    let noise_value = uniforms.cloud_noise.get_noise_3d(position.x + drift, position.y, position.z);
+
+    // La opacidad sale del ruido: cielo despejado donde el ruido es bajo
+    let coverage = smoothstep(-0.05, 0.3, noise_value);
+    let cloud_color = shade_surface(uniforms, fragment, Color::from_srgb(0.95, 0.95, 0.95), 0.0);
+
+    (cloud_color.with_alpha(coverage * 0.85), Color::black())
+}
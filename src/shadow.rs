@@ -0,0 +1,159 @@
+use nalgebra_glm::{look_at, ortho, perspective, Mat4, Vec3, Vec4};
+use serde::Deserialize;
+use crate::culling::BoundingSphere;
+use crate::light::Light;
+use crate::render::create_viewport_matrix;
+
+// Mapa de sombras de la luz principal de la escena (la primera luz puntual o
+// direccional). Se dibuja la profundidad de los cuerpos vista desde la luz y al
+// sombrear se compara cada punto contra ella, con filtrado PCF.
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(default)]
+pub struct ShadowSettings {
+    pub enabled: bool,
+    pub resolution: usize,
+    // Radio en texels del filtro PCF (0 = una sola muestra)
+    pub pcf_radius: i32,
+    // Desplazamiento a lo largo de la normal, en unidades de mundo, contra el acné
+    pub normal_offset: f32,
+}
+
+impl Default for ShadowSettings {
+    fn default() -> Self {
+        ShadowSettings {
+            enabled: true,
+            resolution: 512,
+            pcf_radius: 1,
+            normal_offset: 0.03,
+        }
+    }
+}
+
+// Margen de profundidad (en NDC) para la comparación
+const DEPTH_EPSILON: f32 = 1e-4;
+
+pub struct ShadowMap {
+    // Índice de la luz en `uniforms.lights`
+    pub light: usize,
+    pub view_matrix: Mat4,
+    pub projection_matrix: Mat4,
+    pub viewport_matrix: Mat4,
+    // Profundidad vista desde la luz, de `resolution` x `resolution` texels
+    pub depth: Vec<f32>,
+    pub resolution: usize,
+    pub pcf_radius: i32,
+    pub normal_offset: f32,
+}
+
+impl ShadowMap {
+    pub fn new(settings: &ShadowSettings) -> Self {
+        let resolution = settings.resolution.max(1);
+        ShadowMap {
+            light: 0,
+            view_matrix: Mat4::identity(),
+            projection_matrix: Mat4::identity(),
+            viewport_matrix: create_viewport_matrix(resolution as f32, resolution as f32),
+            depth: vec![f32::INFINITY; resolution * resolution],
+            resolution,
+            pcf_radius: settings.pcf_radius.max(0),
+            normal_offset: settings.normal_offset,
+        }
+    }
+
+    // Ajusta la cámara de la luz para que abarque todos los `casters` y limpia
+    // la profundidad. Devuelve false si no hay luz que proyecte sombras.
+    pub fn prepare(&mut self, lights: &[Light], casters: &[BoundingSphere]) -> bool {
+        self.depth.fill(f32::INFINITY);
+
+        let Some(light) = lights.iter().position(|light| !matches!(light, Light::Ambient { .. })) else {
+            return false;
+        };
+        if casters.is_empty() {
+            return false;
+        }
+        self.light = light;
+
+        let center = casters.iter().fold(Vec3::zeros(), |sum, sphere| sum + sphere.center) / casters.len() as f32;
+        match lights[light] {
+            Light::Point { position, .. } => {
+                let axis = center - position;
+                if axis.magnitude() < 1e-4 {
+                    return false;
+                }
+                let axis = axis.normalize();
+
+                let (mut half_fov, mut near, mut far) = (0.0f32, f32::INFINITY, 0.0f32);
+                for sphere in casters {
+                    let to_sphere = sphere.center - position;
+                    let distance = to_sphere.magnitude();
+                    if distance <= sphere.radius {
+                        continue;
+                    }
+                    let angle = axis.dot(&(to_sphere / distance)).clamp(-1.0, 1.0).acos();
+                    half_fov = half_fov.max(angle + (sphere.radius / distance).asin());
+                    near = near.min(distance - sphere.radius);
+                    far = far.max(distance + sphere.radius);
+                }
+                if far <= 0.0 {
+                    return false;
+                }
+
+                self.view_matrix = look_at(&position, &(position + axis), &up_for(&axis));
+                let fov = (half_fov * 2.0).clamp(0.01, 2.8);
+                self.projection_matrix = perspective(1.0, fov, (near * 0.9).max(0.05), far * 1.1);
+            }
+            Light::Directional { direction, .. } => {
+                let direction = direction.normalize();
+                let radius = casters.iter()
+                    .map(|sphere| (sphere.center - center).magnitude() + sphere.radius)
+                    .fold(0.0, f32::max);
+                let eye = center - direction * (radius * 2.0);
+                self.view_matrix = look_at(&eye, &center, &up_for(&direction));
+                self.projection_matrix = ortho(-radius, radius, -radius, radius, radius * 0.5, radius * 3.5);
+            }
+            Light::Ambient { .. } => return false,
+        }
+
+        true
+    }
+
+    // Fracción de luz (0 a 1) que llega a `position` desde la luz del mapa.
+    pub fn visibility(&self, position: &Vec3, normal: &Vec3) -> f32 {
+        let offset = position + normal * self.normal_offset;
+        let clip = self.projection_matrix * self.view_matrix * Vec4::new(offset.x, offset.y, offset.z, 1.0);
+        if clip.w <= 0.0 {
+            return 1.0;
+        }
+        let screen = self.viewport_matrix * Vec4::new(clip.x / clip.w, clip.y / clip.w, clip.z / clip.w, 1.0);
+        let (x, y, depth) = (screen.x.floor() as i32, screen.y.floor() as i32, screen.z);
+
+        let size = self.resolution as i32;
+        let mut lit = 0;
+        let mut samples = 0;
+        for dy in -self.pcf_radius..=self.pcf_radius {
+            for dx in -self.pcf_radius..=self.pcf_radius {
+                let (sx, sy) = (x + dx, y + dy);
+                samples += 1;
+                if sx < 0 || sy < 0 || sx >= size || sy >= size {
+                    lit += 1;
+                    continue;
+                }
+                let occluder = self.depth[(sy * size + sx) as usize];
+                if depth - DEPTH_EPSILON <= occluder {
+                    lit += 1;
+                }
+            }
+        }
+
+        lit as f32 / samples as f32
+    }
+}
+
+fn up_for(direction: &Vec3) -> Vec3 {
+    if direction.y.abs() > 0.99 {
+        Vec3::new(1.0, 0.0, 0.0)
+    } else {
+        Vec3::new(0.0, 1.0, 0.0)
+    }
+}
@@ -0,0 +1,167 @@
+use nalgebra_glm::{Vec2, Vec3};
+use serde::Deserialize;
+use image::ImageResult;
+use crate::color::{srgb_to_linear, Color};
+
+// Filtro usado al muestrear una textura.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Filter {
+    Nearest,
+    Bilinear,
+    #[default]
+    Trilinear,
+}
+
+// Qué hacer con coordenadas fuera de [0, 1].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WrapMode {
+    #[default]
+    Repeat,
+    MirroredRepeat,
+    ClampToEdge,
+}
+
+struct MipLevel {
+    width: usize,
+    height: usize,
+    texels: Vec<Vec3>,
+}
+
+impl MipLevel {
+    fn texel(&self, x: i32, y: i32, wrap_u: WrapMode, wrap_v: WrapMode) -> Vec3 {
+        let x = wrap(x, self.width, wrap_u);
+        let y = wrap(y, self.height, wrap_v);
+        self.texels[y * self.width + x]
+    }
+
+    fn nearest(&self, uv: Vec2, wrap_u: WrapMode, wrap_v: WrapMode) -> Vec3 {
+        let x = (uv.x * self.width as f32).floor() as i32;
+        let y = (uv.y * self.height as f32).floor() as i32;
+        self.texel(x, y, wrap_u, wrap_v)
+    }
+
+    fn bilinear(&self, uv: Vec2, wrap_u: WrapMode, wrap_v: WrapMode) -> Vec3 {
+        let x = uv.x * self.width as f32 - 0.5;
+        let y = uv.y * self.height as f32 - 0.5;
+        let (x0, y0) = (x.floor(), y.floor());
+        let (tx, ty) = (x - x0, y - y0);
+        let (x0, y0) = (x0 as i32, y0 as i32);
+
+        let top = self.texel(x0, y0, wrap_u, wrap_v).lerp(&self.texel(x0 + 1, y0, wrap_u, wrap_v), tx);
+        let bottom = self.texel(x0, y0 + 1, wrap_u, wrap_v).lerp(&self.texel(x0 + 1, y0 + 1, wrap_u, wrap_v), tx);
+        top.lerp(&bottom, ty)
+    }
+
+    // Siguiente nivel: promedio de bloques de 2x2 texels.
+    fn downsample(&self) -> MipLevel {
+        let width = (self.width / 2).max(1);
+        let height = (self.height / 2).max(1);
+        let mut texels = Vec::with_capacity(width * height);
+
+        for y in 0..height {
+            for x in 0..width {
+                let x0 = (x * 2).min(self.width - 1);
+                let y0 = (y * 2).min(self.height - 1);
+                let x1 = (x * 2 + 1).min(self.width - 1);
+                let y1 = (y * 2 + 1).min(self.height - 1);
+                let sum = self.texels[y0 * self.width + x0]
+                    + self.texels[y0 * self.width + x1]
+                    + self.texels[y1 * self.width + x0]
+                    + self.texels[y1 * self.width + x1];
+                texels.push(sum * 0.25);
+            }
+        }
+
+        MipLevel { width, height, texels }
+    }
+}
+
+// Textura de imagen con su cadena de mipmaps.
+pub struct Texture {
+    levels: Vec<MipLevel>,
+    pub filter: Filter,
+    pub wrap_u: WrapMode,
+    pub wrap_v: WrapMode,
+}
+
+impl Texture {
+    // Las imágenes están en sRGB; se guardan en lineal para que los mipmaps y
+    // el filtrado promedien luz y no valores codificados.
+    pub fn load(filename: &str) -> ImageResult<Self> {
+        let image = image::open(filename)?.to_rgb8();
+        let (width, height) = image.dimensions();
+        let texels = image.pixels()
+            .map(|pixel| {
+                let channel = |value: u8| srgb_to_linear(value as f32 / 255.0);
+                Vec3::new(channel(pixel[0]), channel(pixel[1]), channel(pixel[2]))
+            })
+            .collect();
+
+        Ok(Texture::from_texels(width as usize, height as usize, texels))
+    }
+
+    pub fn from_texels(width: usize, height: usize, texels: Vec<Vec3>) -> Self {
+        let mut levels = vec![MipLevel { width, height, texels }];
+        while let Some(last) = levels.last() {
+            if last.width == 1 && last.height == 1 {
+                break;
+            }
+            let next = last.downsample();
+            levels.push(next);
+        }
+
+        Texture {
+            levels,
+            filter: Filter::default(),
+            wrap_u: WrapMode::default(),
+            wrap_v: WrapMode::default(),
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.levels[0].width
+    }
+
+    pub fn height(&self) -> usize {
+        self.levels[0].height
+    }
+
+    // `footprint` es cuánto cambia la coordenada uv entre un píxel y el
+    // siguiente; con filtro trilineal decide el nivel de mipmap.
+    pub fn sample(&self, uv: Vec2, footprint: f32) -> Color {
+        let value = self.sample_vec3(uv, footprint);
+        Color::from_float(value.x, value.y, value.z)
+    }
+
+    pub fn sample_vec3(&self, uv: Vec2, footprint: f32) -> Vec3 {
+        let base = &self.levels[0];
+        match self.filter {
+            Filter::Nearest => base.nearest(uv, self.wrap_u, self.wrap_v),
+            Filter::Bilinear => base.bilinear(uv, self.wrap_u, self.wrap_v),
+            Filter::Trilinear => {
+                let size = base.width.max(base.height) as f32;
+                let max_level = (self.levels.len() - 1) as f32;
+                let lod = (footprint * size).max(1e-8).log2().clamp(0.0, max_level);
+                let lower = lod.floor() as usize;
+                let upper = (lower + 1).min(self.levels.len() - 1);
+                let near = self.levels[lower].bilinear(uv, self.wrap_u, self.wrap_v);
+                let far = self.levels[upper].bilinear(uv, self.wrap_u, self.wrap_v);
+                near.lerp(&far, lod.fract())
+            }
+        }
+    }
+}
+
+fn wrap(coordinate: i32, size: usize, mode: WrapMode) -> usize {
+    let size = size as i32;
+    match mode {
+        WrapMode::Repeat => coordinate.rem_euclid(size) as usize,
+        WrapMode::ClampToEdge => coordinate.clamp(0, size - 1) as usize,
+        WrapMode::MirroredRepeat => {
+            let period = coordinate.rem_euclid(size * 2);
+            if period < size { period as usize } else { (size * 2 - 1 - period) as usize }
+        }
+    }
+}
@@ -0,0 +1,47 @@
+use serde::Deserialize;
+use crate::color::Color;
+
+// Compresión del color HDR del framebuffer al rango [0, 1] de la pantalla.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ToneMapOperator {
+    // Recorta cada canal a 1.0 (sin tone mapping)
+    #[default]
+    Clamp,
+    Reinhard,
+    // Aproximación de la curva filmica ACES (Narkowicz)
+    Aces,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(default)]
+pub struct ToneMapping {
+    pub operator: ToneMapOperator,
+    // Multiplicador del color antes de aplicar la curva
+    pub exposure: f32,
+}
+
+impl Default for ToneMapping {
+    fn default() -> Self {
+        ToneMapping {
+            operator: ToneMapOperator::default(),
+            exposure: 1.0,
+        }
+    }
+}
+
+impl ToneMapping {
+    pub fn map(&self, color: Color) -> Color {
+        let curve = |value: f32| {
+            let value = (value * self.exposure).max(0.0);
+            match self.operator {
+                ToneMapOperator::Clamp => value.min(1.0),
+                ToneMapOperator::Reinhard => value / (1.0 + value),
+                ToneMapOperator::Aces => {
+                    ((value * (2.51 * value + 0.03)) / (value * (2.43 * value + 0.59) + 0.14)).clamp(0.0, 1.0)
+                }
+            }
+        };
+        Color::from_float(curve(color.r), curve(color.g), curve(color.b))
+    }
+}